@@ -1,53 +1,132 @@
-use std::cell::RefCell;
+use std::collections::HashMap;
 use std::fmt;
-use std::rc::{Rc, Weak};
+use std::str::FromStr;
 
-use crate::file::BirdData;
+use serde::{Deserialize, Serialize};
 
-/// Represents a bird or group in a tree.
-pub enum Node {
-    /// A taxinomical grouping for birds.
-    Group {
-        name: String,
-        parent: RefCell<Weak<Node>>,
-        children: RefCell<Vec<Rc<Node>>>,
-    },
-    /// A bird. This must be at the bottom of the tree, therefore has no children.
-    Bird {
-        name: String,
-        scientific_name: String,
-        parent: RefCell<Weak<Node>>,
-    },
+use crate::file::{BirdData, GroupData};
+use crate::fuzzy;
+
+/// A stable handle to a node stored in a `BirdTree`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct NodeId(usize);
+
+/// The taxonomic rank of a group, ordered from the highest rank to the lowest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Rank {
+    Kingdom,
+    Phylum,
+    Subphylum,
+    Class,
+    Subclass,
+    Infraclass,
+    Superorder,
+    Order,
+    Suborder,
+    Superfamily,
+    Family,
+    Subfamily,
+    Tribe,
+    Genus,
+    Subgenus,
+    Species,
 }
 
-impl fmt::Display for Node {
-    /// Define how a node gets displayed
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl Rank {
+    /// Every rank from highest to lowest.
+    pub const ALL: [Rank; 16] = [
+        Rank::Kingdom,
+        Rank::Phylum,
+        Rank::Subphylum,
+        Rank::Class,
+        Rank::Subclass,
+        Rank::Infraclass,
+        Rank::Superorder,
+        Rank::Order,
+        Rank::Suborder,
+        Rank::Superfamily,
+        Rank::Family,
+        Rank::Subfamily,
+        Rank::Tribe,
+        Rank::Genus,
+        Rank::Subgenus,
+        Rank::Species,
+    ];
+
+    /// The ranks of the groups above a bird when only the principal ranks are used.
+    pub const PRINCIPAL: [Rank; 6] = [
+        Rank::Kingdom,
+        Rank::Phylum,
+        Rank::Class,
+        Rank::Order,
+        Rank::Family,
+        Rank::Genus,
+    ];
+
+    /// Get the name of a rank.
+    pub fn as_str(&self) -> &'static str {
         match self {
-            Node::Group { name, .. } => write!(f, "{}", name),
-            Node::Bird {
-                name,
-                scientific_name,
-                ..
-            } => write!(
-                f,
-                "{name}\n{scientific_name}\n{full_scientific_name}",
-                full_scientific_name = self.full_scientific_name().unwrap_or("".to_string()),
-            ),
+            Rank::Kingdom => "kingdom",
+            Rank::Phylum => "phylum",
+            Rank::Subphylum => "subphylum",
+            Rank::Class => "class",
+            Rank::Subclass => "subclass",
+            Rank::Infraclass => "infraclass",
+            Rank::Superorder => "superorder",
+            Rank::Order => "order",
+            Rank::Suborder => "suborder",
+            Rank::Superfamily => "superfamily",
+            Rank::Family => "family",
+            Rank::Subfamily => "subfamily",
+            Rank::Tribe => "tribe",
+            Rank::Genus => "genus",
+            Rank::Subgenus => "subgenus",
+            Rank::Species => "species",
         }
     }
 }
 
+impl fmt::Display for Rank {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
 #[derive(Debug)]
-pub struct NodeTypeError;
+pub struct ParseRankError;
+
+impl FromStr for Rank {
+    type Err = ParseRankError;
+
+    /// Parse a rank from its name, ignoring case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        Rank::ALL
+            .into_iter()
+            .find(|rank| rank.as_str().eq_ignore_ascii_case(s))
+            .ok_or(ParseRankError)
+    }
+}
+
+/// Represents a bird or group in a tree.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Node {
+    /// A taxinomical grouping for birds. Groups without a rank are treated as unranked clades.
+    Group { name: String, rank: Option<Rank> },
+    /// A bird. This must be at the bottom of the tree, therefore has no children.
+    Bird {
+        name: String,
+        scientific_name: String,
+    },
+}
 
 impl Node {
     /// Create a new group Node.
-    pub fn new_group(name: &str) -> Self {
+    pub fn new_group(name: &str, rank: Option<Rank>) -> Self {
         Node::Group {
             name: name.to_string(),
-            children: RefCell::new(vec![]),
-            parent: RefCell::new(Weak::new()),
+            rank,
         }
     }
 
@@ -56,23 +135,6 @@ impl Node {
         Node::Bird {
             name: name.to_string(),
             scientific_name: scientific_name.to_string(),
-            parent: RefCell::new(Weak::new()),
-        }
-    }
-
-    /// Get the parent of a Node.
-    pub fn parent(&self) -> &RefCell<Weak<Node>> {
-        match self {
-            Node::Bird { parent, .. } => parent,
-            Node::Group { parent, .. } => parent,
-        }
-    }
-
-    /// Get the children of a Node.
-    pub fn children(&self) -> Result<&RefCell<Vec<Rc<Node>>>, NodeTypeError> {
-        match self {
-            Node::Group { children, .. } => Ok(children),
-            _ => Err(NodeTypeError),
         }
     }
 
@@ -95,34 +157,17 @@ impl Node {
         }
     }
 
-    /// Get the full scientific name of a bird.
-    /// This is essentially a path of all the parents' names
-    pub fn full_scientific_name(&self) -> Option<String> {
-        Some(format!(
-            "{} {}",
-            self.parent()
-                .borrow()
-                .upgrade()?
-                .full_scientific_name()
-                .unwrap_or("".to_string())
-                .trim(),
-            self.scientific_name()
-        ))
-    }
-
-    /// Add a node to a group node.
-    /// Returns Err(NodeTypeError) if this function is called on a `Node::Bird` as a `Node::Bird`
-    /// has no children.
-    pub fn add(self: Rc<Self>, child: Rc<Self>) -> Result<(), NodeTypeError> {
-        match &*self {
-            Node::Group { children, .. } => {
-                *child.parent().borrow_mut() = Rc::downgrade(&self);
-                children.borrow_mut().push(child);
-            }
-            _ => return Err(NodeTypeError),
+    /// Get the rank of a Node. A `Node::Bird` has no rank of its own.
+    pub fn rank(&self) -> Option<Rank> {
+        match self {
+            Node::Group { rank, .. } => *rank,
+            Node::Bird { .. } => None,
         }
+    }
 
-        Ok(())
+    /// Check whether the node is a group.
+    pub fn is_group(&self) -> bool {
+        matches!(self, Node::Group { .. })
     }
 }
 
@@ -130,269 +175,1449 @@ impl Node {
 pub enum GroupError {
     NoGroupExistsErr,
     InputOutsideOfBoundsError,
+    /// The parent of a new node is a `Node::Bird`, which can't have children.
+    NodeTypeError,
+    /// A group would sit below a group of the same or a lower rank.
+    RankOrderError {
+        parent: Rank,
+        child: Rank,
+    },
+    NoBirdExistsErr,
+    /// A group still contains birds or groups, and wasn't removed.
+    GroupNotEmptyError,
+    /// The root of the tree can't be removed or moved.
+    RootNodeError,
+    /// A group can't be moved inside itself.
+    CycleError,
+    /// Another bird or group already uses the name.
+    DuplicateNameError,
+}
+
+/// How to remove a group that still has children.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RemoveMode {
+    /// Refuse to remove a group that isn't empty.
+    EmptyOnly,
+    /// Remove the group along with everything inside it.
+    Cascade,
+}
+
+/// How closely a name must match to be returned by a fuzzy search, from 0 to 1.
+const FUZZY_THRESHOLD: f64 = 0.6;
+
+/// A bird found by a fuzzy search.
+#[derive(Clone, Copy, Debug)]
+pub struct FuzzyMatch {
+    pub id: NodeId,
+    /// How alike the closest of the bird's names is to the search, where 1 is the same name
+    pub score: f64,
+}
+
+/// A group or bird from a data file that could not be added to a tree.
+#[derive(Debug)]
+pub struct RejectedData {
+    /// Either "group" or "bird"
+    pub kind: &'static str,
+    /// The position of the record in its list, starting at 0
+    pub index: usize,
+    /// The name of the group, or the common name of the bird
+    pub name: String,
+    /// The group in the record's path whose name is already used elsewhere in the tree, when
+    /// that is why the record was rejected
+    pub clash: Option<String>,
+    pub error: GroupError,
+}
+
+/// A node stored in the tree along with the links to its parent and children.
+#[derive(Clone, Serialize, Deserialize)]
+struct Entry {
+    node: Node,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+}
+
+/// The storage of a tree. Nodes refer to each other by their index in `nodes`.
+#[derive(Clone, Serialize, Deserialize)]
+struct Arena {
+    root: NodeId,
+    nodes: Vec<Option<Entry>>,
+}
+
+/// An error for a serialized tree whose nodes don't describe a valid bird tree.
+#[derive(Debug)]
+pub struct InvalidTreeError;
+
+impl fmt::Display for InvalidTreeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "nodes do not form a valid bird tree")
+    }
+}
+
+impl TryFrom<Arena> for BirdTree {
+    type Error = InvalidTreeError;
+
+    /// Check a deserialized tree and rebuild its name indexes. The links must describe a single
+    /// tree, and every node must follow the same rules as when it is added with `add`.
+    fn try_from(arena: Arena) -> Result<Self, Self::Error> {
+        let get = |id: NodeId| arena.nodes.get(id.0).and_then(Option::as_ref);
+
+        // the root must be a group without a parent
+        match get(arena.root) {
+            Some(entry) if entry.node.is_group() && entry.parent.is_none() => {}
+            _ => return Err(InvalidTreeError),
+        }
+
+        // every link must point at an existing node and agree with the link going the other way
+        for (index, entry) in arena.nodes.iter().enumerate() {
+            let Some(entry) = entry else { continue };
+            let id = NodeId(index);
+
+            if let Some(parent) = entry.parent
+                && !get(parent).is_some_and(|p| p.children.contains(&id))
+            {
+                return Err(InvalidTreeError);
+            }
+            for child in entry.children.iter() {
+                if get(*child).and_then(|c| c.parent) != Some(id) {
+                    return Err(InvalidTreeError);
+                }
+            }
+        }
+
+        // every node must be reachable from the root exactly once, which also rules out cycles
+        let mut visited = vec![false; arena.nodes.len()];
+        let mut stack = vec![arena.root];
+        while let Some(id) = stack.pop() {
+            if std::mem::replace(&mut visited[id.0], true) {
+                return Err(InvalidTreeError);
+            }
+            stack.extend(get(id).into_iter().flat_map(|entry| entry.children.iter()));
+        }
+        if visited
+            .iter()
+            .zip(arena.nodes.iter())
+            .any(|(v, e)| !v && e.is_some())
+        {
+            return Err(InvalidTreeError);
+        }
+
+        let mut tree = Self {
+            arena,
+            birds_by_name: HashMap::new(),
+            birds_by_scientific_name: HashMap::new(),
+            birds_by_binomial_name: HashMap::new(),
+            groups_by_name: HashMap::new(),
+        };
+        // check each node against the ones above and before it, as if they were added in order
+        for id in tree.preorder(tree.root()) {
+            if let (Some(parent), Some(node)) = (tree.parent(id), tree.get(id)) {
+                tree.check_add(parent, node).map_err(|_| InvalidTreeError)?;
+            }
+            tree.index_node(id);
+        }
+
+        Ok(tree)
+    }
+}
+
+/// Normalize a name so that lookups ignore case and surrounding whitespace.
+fn normalize(name: &str) -> String {
+    name.trim().to_lowercase()
 }
 
-/// Holds references to important nodes on the tree.
+/// Add a node to a name index.
+fn index_insert(index: &mut HashMap<String, Vec<NodeId>>, name: &str, id: NodeId) {
+    index.entry(normalize(name)).or_default().push(id);
+}
+
+/// Remove a node from a name index.
+fn index_remove(index: &mut HashMap<String, Vec<NodeId>>, name: &str, id: NodeId) {
+    let key = normalize(name);
+    if let Some(ids) = index.get_mut(&key) {
+        ids.retain(|other| *other != id);
+        if ids.is_empty() {
+            index.remove(&key);
+        }
+    }
+}
+
+/// Get the first node in a name index with a name.
+fn index_get(index: &HashMap<String, Vec<NodeId>>, name: &str) -> Option<NodeId> {
+    index.get(&normalize(name))?.first().copied()
+}
+
+/// A bird tree stored in an arena of nodes. It serializes as its arena, and the name indexes
+/// are rebuilt when it is deserialized.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "Arena")]
 pub struct BirdTree {
-    /// Tree root node
-    pub root: Rc<Node>,
+    /// Every node in the tree, addressed by `NodeId`
+    #[serde(flatten)]
+    arena: Arena,
+
+    /// Birds indexed by their normalized common name
+    #[serde(skip)]
+    birds_by_name: HashMap<String, Vec<NodeId>>,
+
+    /// Birds indexed by their normalized species epithet
+    #[serde(skip)]
+    birds_by_scientific_name: HashMap<String, Vec<NodeId>>,
+
+    /// Birds indexed by their normalized binomial name, which depends on the groups above them
+    #[serde(skip)]
+    birds_by_binomial_name: HashMap<String, Vec<NodeId>>,
+
+    /// Groups indexed by their normalized name
+    #[serde(skip)]
+    groups_by_name: HashMap<String, Vec<NodeId>>,
+}
 
-    /// Nodes with only 1 child depth
-    pub direct_parents: Vec<Rc<Node>>,
+/// The binomial (or trinomial) name of a bird, e.g. "Nestor meridionalis".
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScientificName {
+    /// The genus, with a capitalised first letter
+    pub genus: String,
+    /// The species epithet in lowercase
+    pub species: String,
+    /// The subspecies epithet in lowercase, for trinomial names
+    pub subspecies: Option<String>,
+}
+
+impl ScientificName {
+    /// Build a scientific name, fixing the casing of each part.
+    pub fn new(genus: &str, species: &str, subspecies: Option<&str>) -> Self {
+        let genus = genus.trim().to_lowercase();
+        let mut chars = genus.chars();
+        let genus = match chars.next() {
+            Some(first) => first.to_uppercase().chain(chars).collect(),
+            None => String::new(),
+        };
+
+        Self {
+            genus,
+            species: species.trim().to_lowercase(),
+            subspecies: subspecies.map(|s| s.trim().to_lowercase()),
+        }
+    }
+
+    /// Get the name wrapped in the terminal escape codes for italics.
+    pub fn to_ansi(&self) -> String {
+        format!("\x1b[3m{}\x1b[23m", self)
+    }
+}
+
+impl fmt::Display for ScientificName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.genus, self.species)?;
+        if let Some(subspecies) = &self.subspecies {
+            write!(f, " {}", subspecies)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Displays a node with the details that can only be found from the tree it is in.
+/// The alternate flag (`{:#}`) prints the scientific name in italics for terminals.
+pub struct NodeDisplay<'a> {
+    tree: &'a BirdTree,
+    id: NodeId,
+}
+
+impl fmt::Display for NodeDisplay<'_> {
+    /// Define how a node gets displayed
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.tree.get(self.id) {
+            None => Ok(()),
+            Some(Node::Group { name, .. }) => write!(f, "{}", name),
+            Some(Node::Bird {
+                name,
+                scientific_name,
+            }) => write!(
+                f,
+                "{name}\n{binomial_name}\n{classification}",
+                binomial_name = match self.tree.binomial_name(self.id) {
+                    Some(name) if f.alternate() => name.to_ansi(),
+                    Some(name) => name.to_string(),
+                    None => scientific_name.to_string(),
+                },
+                classification = self
+                    .tree
+                    .classification_path(self.id)
+                    .unwrap_or("".to_string()),
+            ),
+        }
+    }
 }
 
 impl BirdTree {
-    /// Build a new bird tree.
-    /// Returns None if root or one of the direct parents is a `Node::Bird`
-    pub fn new(root: Rc<Node>, direct_parents: Vec<Rc<Node>>) -> Option<Self> {
+    /// Build a new bird tree containing only a root.
+    /// Returns None if root is a `Node::Bird`
+    pub fn new(root: Node) -> Option<Self> {
         // assure that root is a group
-        if let Node::Bird { .. } = &*root {
+        if !root.is_group() {
             return None;
         }
 
-        // assure that all direct parents are groups
-        for node in direct_parents.iter() {
-            if let Node::Bird { .. } = &**node {
-                return None;
-            }
+        let mut tree = Self {
+            arena: Arena {
+                root: NodeId(0),
+                nodes: vec![Some(Entry {
+                    node: root,
+                    parent: None,
+                    children: vec![],
+                })],
+            },
+            birds_by_name: HashMap::new(),
+            birds_by_scientific_name: HashMap::new(),
+            birds_by_binomial_name: HashMap::new(),
+            groups_by_name: HashMap::new(),
+        };
+        tree.index_node(NodeId(0));
+
+        Some(tree)
+    }
+
+    /// Get the entry of a node.
+    fn entry(&self, id: NodeId) -> Option<&Entry> {
+        self.arena.nodes.get(id.0)?.as_ref()
+    }
+
+    /// Get the tree root node.
+    pub fn root(&self) -> NodeId {
+        self.arena.root
+    }
+
+    /// Get a node from its id.
+    pub fn get(&self, id: NodeId) -> Option<&Node> {
+        Some(&self.entry(id)?.node)
+    }
+
+    /// Get the parent of a node.
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.entry(id)?.parent
+    }
+
+    /// Get the children of a node. A `Node::Bird` has no children.
+    pub fn children(&self, id: NodeId) -> &[NodeId] {
+        match self.entry(id) {
+            Some(entry) => &entry.children,
+            None => &[],
         }
+    }
 
-        // build the BirdTree
-        Some(Self {
-            root,
-            direct_parents,
-        })
+    /// Get a node that can be displayed.
+    pub fn display(&self, id: NodeId) -> NodeDisplay<'_> {
+        NodeDisplay { tree: self, id }
     }
 
-    /// Find a bird node from its scientific name
-    pub fn search_by_scientific_name(&self, name: &str) -> Option<Rc<Node>> {
-        for group in self.direct_parents.iter() {
-            // can safely unwrap due BirdTree assuring that direct_parents only contains groups
-            let children = group.children().unwrap().borrow();
+    /// Check whether a node sits somewhere below another node in the tree.
+    pub fn is_descendant_of(&self, id: NodeId, ancestor: NodeId) -> bool {
+        let mut current = self.parent(id);
+        while let Some(node) = current {
+            if node == ancestor {
+                return true;
+            }
+            current = self.parent(node);
+        }
 
-            // search through all direct parents to find the bird
-            for child in children.iter() {
-                if child.scientific_name().to_lowercase().trim() == name.to_lowercase().trim() {
-                    return Some(Rc::clone(child));
-                }
+        false
+    }
+
+    /// Get the rank of the closest ranked group at or above a node.
+    pub fn nearest_rank(&self, id: NodeId) -> Option<Rank> {
+        let mut current = Some(id);
+        while let Some(node) = current {
+            if let Some(rank) = self.get(node)?.rank() {
+                return Some(rank);
             }
+            current = self.parent(node);
         }
 
         None
     }
 
-    /// Find a bird node from its common name
-    pub fn search_by_name(&self, name: &str) -> Option<Rc<Node>> {
-        for group in self.direct_parents.iter() {
-            // can safely unwrap due BirdTree assuring that direct_parents only contains groups
-            let children = group.children().unwrap().borrow();
-
-            // search through all direct parents to find the bird
-            for child in children.iter() {
-                if child.name().to_lowercase().trim() == name.to_lowercase().trim() {
-                    return Some(Rc::clone(child));
-                }
+    /// Get the closest group at or above a node with a rank.
+    pub fn ancestor_with_rank(&self, id: NodeId, rank: Rank) -> Option<NodeId> {
+        let mut current = Some(id);
+        while let Some(node) = current {
+            if self.get(node)?.rank() == Some(rank) {
+                return Some(node);
             }
+            current = self.parent(node);
         }
 
         None
     }
 
-    /// Get a group from name searching starting at a certain group
-    fn get_group_with_name(group: Rc<Node>, group_name: &str) -> Option<Rc<Node>> {
-        if let Node::Bird { .. } = &*group {
+    /// Get the ids of a node and every node below it, with each group before its children.
+    pub fn preorder(&self, id: NodeId) -> Vec<NodeId> {
+        let mut nodes = vec![];
+        let mut stack = vec![id];
+        while let Some(node) = stack.pop() {
+            nodes.push(node);
+            stack.extend(self.children(node).iter().rev());
+        }
+
+        nodes
+    }
+
+    /// Get the ids of every group above a node, starting at the root.
+    pub fn lineage(&self, id: NodeId) -> Vec<NodeId> {
+        let mut lineage = vec![];
+        let mut current = self.parent(id);
+        while let Some(node) = current {
+            lineage.push(node);
+            current = self.parent(node);
+        }
+        lineage.reverse();
+
+        lineage
+    }
+
+    /// Get the full classification of a node, which is the path of names from the root down to
+    /// and including the node, in the casing they were entered with.
+    pub fn classification_path(&self, id: NodeId) -> Option<String> {
+        let mut names = vec![];
+        for node in self.lineage(id).into_iter().chain([id]) {
+            names.push(self.get(node)?.scientific_name());
+        }
+
+        Some(names.join(" > "))
+    }
+
+    /// Get the binomial name of a bird, made from its genus and species epithet.
+    /// Birds inside a species group are subspecies, and get a trinomial name instead.
+    /// Falls back to the name of the parent group when the bird has no genus above it.
+    pub fn binomial_name(&self, id: NodeId) -> Option<ScientificName> {
+        let Node::Bird {
+            scientific_name, ..
+        } = self.get(id)?
+        else {
             return None;
+        };
+        let parent = self.parent(id)?;
+
+        let (species, subspecies, genus_search_start) =
+            if self.get(parent)?.rank() == Some(Rank::Species) {
+                let species = self.get(parent)?.name();
+                (species, Some(scientific_name), self.parent(parent)?)
+            } else {
+                (scientific_name.as_str(), None, parent)
+            };
+        let genus = self
+            .ancestor_with_rank(genus_search_start, Rank::Genus)
+            .unwrap_or(genus_search_start);
+
+        Some(ScientificName::new(
+            self.get(genus)?.name(),
+            species,
+            subspecies.map(String::as_str),
+        ))
+    }
+
+    /// Check whether a node in `parent` would use a name that must be unique. Group names and
+    /// the common names of birds are unique in the whole tree, and species epithets are unique
+    /// within their genus. `moving` is the node itself when it is already in the tree, so that
+    /// it doesn't clash with its own names.
+    fn is_duplicate(&self, parent: NodeId, node: &Node, moving: Option<NodeId>) -> bool {
+        let used = |index: &HashMap<String, Vec<NodeId>>, name: &str| {
+            index
+                .get(&normalize(name))
+                .is_some_and(|ids| ids.iter().any(|other| Some(*other) != moving))
+        };
+
+        match node {
+            Node::Group { name, .. } => used(&self.groups_by_name, name),
+            Node::Bird {
+                name,
+                scientific_name,
+            } => {
+                used(&self.birds_by_name, name)
+                    || self
+                        .birds_by_scientific_name
+                        .get(&normalize(scientific_name))
+                        .is_some_and(|ids| {
+                            ids.iter().any(|other| {
+                                Some(*other) != moving && self.parent(*other) == Some(parent)
+                            })
+                        })
+            }
+        }
+    }
+
+    /// Check that a node can be added to a group node, for the same reasons as `add` checks.
+    fn check_add(&self, parent: NodeId, node: &Node) -> Result<(), GroupError> {
+        match self.get(parent) {
+            Some(Node::Group { .. }) => {}
+            _ => return Err(GroupError::NodeTypeError),
+        }
+
+        // ranks must descend from the root down to the birds
+        if let (Some(parent_rank), Some(child_rank)) = (self.nearest_rank(parent), node.rank())
+            && child_rank <= parent_rank
+        {
+            return Err(GroupError::RankOrderError {
+                parent: parent_rank,
+                child: child_rank,
+            });
+        }
+        if self.is_duplicate(parent, node, None) {
+            return Err(GroupError::DuplicateNameError);
+        }
+
+        Ok(())
+    }
+
+    /// Add a node to a group node, returning the id of the new node.
+    /// Returns Err(GroupError::NodeTypeError) if `parent` is a `Node::Bird` as a `Node::Bird`
+    /// has no children, Err(GroupError::RankOrderError) if a ranked group would not sit
+    /// below every ranked group above it, and Err(GroupError::DuplicateNameError) if the name is
+    /// already used in the same way as `set_name` and `set_scientific_name` check.
+    pub fn add(&mut self, parent: NodeId, node: Node) -> Result<NodeId, GroupError> {
+        self.check_add(parent, &node)?;
+
+        let id = NodeId(self.arena.nodes.len());
+        self.arena.nodes.push(Some(Entry {
+            node,
+            parent: Some(parent),
+            children: vec![],
+        }));
+        if let Some(Some(entry)) = self.arena.nodes.get_mut(parent.0) {
+            entry.children.push(id);
+        }
+
+        self.index_node(id);
+
+        Ok(id)
+    }
+
+    /// Remove a node from the tree, returning the removed nodes.
+    /// A group that still has children is only removed along with them when using
+    /// `RemoveMode::Cascade`. The ids of removed nodes are never reused.
+    pub fn remove(&mut self, id: NodeId, mode: RemoveMode) -> Result<Vec<Node>, GroupError> {
+        let Some(parent) = self.parent(id) else {
+            return match self.get(id) {
+                Some(_) => Err(GroupError::RootNodeError),
+                None => Err(GroupError::NoGroupExistsErr),
+            };
+        };
+        if mode == RemoveMode::EmptyOnly && !self.children(id).is_empty() {
+            return Err(GroupError::GroupNotEmptyError);
+        }
+
+        // binomial names are found through the groups above, so unindex before unlinking
+        self.unindex_subtree(id);
+
+        // detach the node from its parent
+        if let Some(Some(entry)) = self.arena.nodes.get_mut(parent.0) {
+            entry.children.retain(|child| *child != id);
+        }
+
+        // clear out the node and everything below it
+        let mut removed = vec![];
+        let mut stack = vec![id];
+        while let Some(node) = stack.pop() {
+            stack.extend_from_slice(self.children(node));
+            if let Some(entry) = self.arena.nodes.get_mut(node.0).and_then(Option::take) {
+                removed.push(entry.node);
+            }
+        }
+
+        Ok(removed)
+    }
+
+    /// Get the highest rank of any group at or below a node.
+    fn highest_rank_in_subtree(&self, id: NodeId) -> Option<Rank> {
+        let mut highest = self.get(id)?.rank();
+        for child in self.children(id) {
+            highest = match (highest, self.highest_rank_in_subtree(*child)) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            };
+        }
+
+        highest
+    }
+
+    /// Move a node, along with everything below it, into a different group.
+    /// Returns Err(GroupError::CycleError) if the group would end up inside itself, and the same
+    /// errors as `add` if the new parent is a bird, the ranks would no longer descend or a bird
+    /// with the same species epithet is already in the new group.
+    pub fn move_node(&mut self, id: NodeId, new_parent: NodeId) -> Result<(), GroupError> {
+        let Some(old_parent) = self.parent(id) else {
+            return match self.get(id) {
+                Some(_) => Err(GroupError::RootNodeError),
+                None => Err(GroupError::NoGroupExistsErr),
+            };
+        };
+        match self.get(new_parent) {
+            Some(Node::Group { .. }) => {}
+            Some(Node::Bird { .. }) => return Err(GroupError::NodeTypeError),
+            None => return Err(GroupError::NoGroupExistsErr),
+        }
+        if new_parent == id || self.is_descendant_of(new_parent, id) {
+            return Err(GroupError::CycleError);
+        }
+
+        // every group being moved must still sit below the ranked groups above it
+        if let (Some(parent_rank), Some(child_rank)) = (
+            self.nearest_rank(new_parent),
+            self.highest_rank_in_subtree(id),
+        ) && child_rank <= parent_rank
+        {
+            return Err(GroupError::RankOrderError {
+                parent: parent_rank,
+                child: child_rank,
+            });
+        }
+        if self
+            .get(id)
+            .is_some_and(|node| self.is_duplicate(new_parent, node, Some(id)))
+        {
+            return Err(GroupError::DuplicateNameError);
+        }
+
+        // the binomial names of birds can change with the groups above them
+        self.unindex_subtree(id);
+        if let Some(Some(entry)) = self.arena.nodes.get_mut(old_parent.0) {
+            entry.children.retain(|child| *child != id);
+        }
+        if let Some(Some(entry)) = self.arena.nodes.get_mut(new_parent.0) {
+            entry.children.push(id);
+        }
+        if let Some(Some(entry)) = self.arena.nodes.get_mut(id.0) {
+            entry.parent = Some(new_parent);
+        }
+        self.index_subtree(id);
+
+        Ok(())
+    }
+
+    /// Change the name of a node. For a bird, this is its common name.
+    /// Returns Err(GroupError::DuplicateNameError) if another group, or another bird, already
+    /// has the name.
+    pub fn set_name(&mut self, id: NodeId, new_name: &str) -> Result<(), GroupError> {
+        let index = match self.get(id) {
+            Some(Node::Group { .. }) => &self.groups_by_name,
+            Some(Node::Bird { .. }) => &self.birds_by_name,
+            None => return Err(GroupError::NoGroupExistsErr),
+        };
+        if index
+            .get(&normalize(new_name))
+            .is_some_and(|ids| ids.iter().any(|other| *other != id))
+        {
+            return Err(GroupError::DuplicateNameError);
+        }
+
+        // renaming a genus changes the binomial names of the birds below it
+        self.unindex_subtree(id);
+        if let Some(Some(entry)) = self.arena.nodes.get_mut(id.0) {
+            match &mut entry.node {
+                Node::Group { name, .. } | Node::Bird { name, .. } => *name = new_name.to_string(),
+            }
+        }
+        self.index_subtree(id);
+
+        Ok(())
+    }
+
+    /// Change the scientific name of a bird.
+    /// Returns Err(GroupError::DuplicateNameError) if another bird in the same group already has
+    /// the name.
+    pub fn set_scientific_name(
+        &mut self,
+        id: NodeId,
+        new_scientific_name: &str,
+    ) -> Result<(), GroupError> {
+        match self.get(id) {
+            Some(Node::Bird { .. }) => {}
+            Some(Node::Group { .. }) => return Err(GroupError::NodeTypeError),
+            None => return Err(GroupError::NoBirdExistsErr),
         }
 
-        for child in group.children().unwrap().borrow().iter() {
-            if let Node::Group { name, .. } = &**child {
-                // return the group if the name matches
-                if name.to_lowercase().trim() == group_name.to_lowercase().trim() {
-                    return Some(Rc::clone(&child));
+        // species epithets are only unique within their genus
+        let parent = self.parent(id);
+        if self
+            .birds_by_scientific_name
+            .get(&normalize(new_scientific_name))
+            .is_some_and(|ids| {
+                ids.iter()
+                    .any(|other| *other != id && self.parent(*other) == parent)
+            })
+        {
+            return Err(GroupError::DuplicateNameError);
+        }
+
+        self.unindex_node(id);
+        if let Some(Some(entry)) = self.arena.nodes.get_mut(id.0)
+            && let Node::Bird {
+                scientific_name, ..
+            } = &mut entry.node
+        {
+            *scientific_name = new_scientific_name.to_string();
+        }
+        self.index_node(id);
+
+        Ok(())
+    }
+
+    /// Add a node and everything below it to the name indexes.
+    fn index_subtree(&mut self, id: NodeId) {
+        for node in self.preorder(id) {
+            self.index_node(node);
+        }
+    }
+
+    /// Remove a node and everything below it from the name indexes.
+    fn unindex_subtree(&mut self, id: NodeId) {
+        for node in self.preorder(id) {
+            self.unindex_node(node);
+        }
+    }
+
+    /// Add a single node to the name indexes. The node must already be linked into the tree so
+    /// that its binomial name can be found.
+    fn index_node(&mut self, id: NodeId) {
+        let binomial_name = self.binomial_name(id).map(|name| name.to_string());
+        let Some(entry) = self.arena.nodes.get(id.0).and_then(Option::as_ref) else {
+            return;
+        };
+
+        match &entry.node {
+            Node::Group { name, .. } => index_insert(&mut self.groups_by_name, name, id),
+            Node::Bird {
+                name,
+                scientific_name,
+            } => {
+                index_insert(&mut self.birds_by_name, name, id);
+                index_insert(&mut self.birds_by_scientific_name, scientific_name, id);
+                if let Some(binomial_name) = binomial_name {
+                    index_insert(&mut self.birds_by_binomial_name, &binomial_name, id);
                 }
             }
         }
+    }
+
+    /// Remove a single node from the name indexes, while it is still linked into the tree.
+    fn unindex_node(&mut self, id: NodeId) {
+        let binomial_name = self.binomial_name(id).map(|name| name.to_string());
+        let Some(entry) = self.arena.nodes.get(id.0).and_then(Option::as_ref) else {
+            return;
+        };
 
-        // if the child cannot be found in this group, call the function recursively for all
-        // children
-        for child in group.children().unwrap().borrow().iter() {
-            if let Node::Group { .. } = &**child {
-                if let Some(group) = Self::get_group_with_name(Rc::clone(&child), &group_name) {
-                    return Some(group);
+        match &entry.node {
+            Node::Group { name, .. } => index_remove(&mut self.groups_by_name, name, id),
+            Node::Bird {
+                name,
+                scientific_name,
+            } => {
+                index_remove(&mut self.birds_by_name, name, id);
+                index_remove(&mut self.birds_by_scientific_name, scientific_name, id);
+                if let Some(binomial_name) = binomial_name {
+                    index_remove(&mut self.birds_by_binomial_name, &binomial_name, id);
                 }
             }
         }
+    }
 
-        None
+    /// Find a bird node from its scientific name, either the binomial name, e.g.
+    /// "Nestor notabilis", or the species epithet alone, e.g. "notabilis". An epithet that is
+    /// used in more than one genus finds nothing, as it doesn't say which bird is meant.
+    pub fn search_by_scientific_name(&self, name: &str) -> Option<NodeId> {
+        if let Some(bird) = index_get(&self.birds_by_binomial_name, name) {
+            return Some(bird);
+        }
+
+        match self
+            .birds_by_scientific_name
+            .get(&normalize(name))?
+            .as_slice()
+        {
+            [bird] => Some(*bird),
+            _ => None,
+        }
+    }
+
+    /// Find a bird node from its common name
+    pub fn search_by_name(&self, name: &str) -> Option<NodeId> {
+        index_get(&self.birds_by_name, name)
+    }
+
+    /// Find the birds whose common or scientific name is close to `query`, ignoring case and
+    /// accents, e.g. "Tui" finds the Tūī. The best matches come first, and at most `limit` are
+    /// returned. Scientific names are compared both as the binomial and the species epithet.
+    pub fn fuzzy_search(&self, query: &str, limit: usize) -> Vec<FuzzyMatch> {
+        let mut matches = self
+            .preorder(self.root())
+            .into_iter()
+            .filter_map(|id| {
+                let Some(Node::Bird {
+                    name,
+                    scientific_name,
+                }) = self.get(id)
+                else {
+                    return None;
+                };
+                let binomial_name = self.binomial_name(id).map(|name| name.to_string());
+
+                let score = [Some(name), Some(scientific_name), binomial_name.as_ref()]
+                    .into_iter()
+                    .flatten()
+                    .map(|name| fuzzy::match_score(query, name))
+                    .fold(0.0, f64::max);
+                (score >= FUZZY_THRESHOLD).then_some(FuzzyMatch { id, score })
+            })
+            .collect::<Vec<_>>();
+
+        // the closest first, then in alphabetical order
+        matches.sort_by(|a, b| {
+            b.score.total_cmp(&a.score).then_with(|| {
+                self.get(a.id)
+                    .map(Node::name)
+                    .cmp(&self.get(b.id).map(Node::name))
+            })
+        });
+        matches.truncate(limit);
+
+        matches
+    }
+
+    /// Get a group anywhere in the tree from its name
+    pub fn get_group_with_name(&self, group_name: &str) -> Option<NodeId> {
+        index_get(&self.groups_by_name, group_name)
+    }
+
+    /// Get a group directly inside another group from its name
+    pub fn get_child_group(&self, group: NodeId, group_name: &str) -> Option<NodeId> {
+        self.groups_by_name
+            .get(&normalize(group_name))?
+            .iter()
+            .find(|candidate| self.parent(**candidate) == Some(group))
+            .copied()
+    }
+
+    /// Count the birds in a node and everything below it.
+    pub fn count_birds(&self, id: NodeId) -> usize {
+        match self.get(id) {
+            Some(Node::Bird { .. }) => 1,
+            Some(Node::Group { .. }) => self
+                .children(id)
+                .iter()
+                .map(|child| self.count_birds(*child))
+                .sum(),
+            None => 0,
+        }
     }
 
     /// Recursively get birds in a group.
     /// Birds will get added to the accumulator Vec.
-    fn birds_in_group(acc: &mut Vec<Rc<Node>>, group: Rc<Node>) {
-        for child in group.children().unwrap().borrow().iter() {
-            match &**child {
-                Node::Bird { .. } => acc.push(Rc::clone(child)),
-                Node::Group { .. } => Self::birds_in_group(acc, Rc::clone(child)),
+    fn birds_in_group(&self, acc: &mut Vec<NodeId>, group: NodeId) {
+        for child in self.children(group).iter() {
+            match self.get(*child) {
+                Some(Node::Bird { .. }) => acc.push(*child),
+                Some(Node::Group { .. }) => self.birds_in_group(acc, *child),
+                None => {}
             }
         }
     }
 
     /// Get all birds in a group from a group name.
-    pub fn birds_in_group_from_name(&self, group_name: &str) -> Result<Vec<Rc<Node>>, GroupError> {
-        let group = match Self::get_group_with_name(Rc::clone(&self.root), group_name) {
+    pub fn birds_in_group_from_name(&self, group_name: &str) -> Result<Vec<NodeId>, GroupError> {
+        let group = match self.get_group_with_name(group_name) {
             Some(group) => group,
             None => return Err(GroupError::NoGroupExistsErr),
         };
 
         let mut birds = vec![];
 
-        Self::birds_in_group(&mut birds, Rc::clone(&group));
+        self.birds_in_group(&mut birds, group);
 
-        return Ok(birds);
+        Ok(birds)
     }
 
     // add a group to the tree by name of group and parent
-    pub fn add_group(&self, parent: &str, new_group_name: &str) -> Result<(), GroupError> {
-        if new_group_name.len() < 1 || new_group_name.len() > 50 {
+    pub fn add_group(
+        &mut self,
+        parent: &str,
+        new_group_name: &str,
+        rank: Option<Rank>,
+    ) -> Result<(), GroupError> {
+        if new_group_name.is_empty() || new_group_name.len() > 50 {
             return Err(GroupError::InputOutsideOfBoundsError);
         }
-        let parent_group = match Self::get_group_with_name(Rc::clone(&self.root), parent) {
+        let parent_group = match self.get_group_with_name(parent) {
             Some(group) => group,
             None => return Err(GroupError::NoGroupExistsErr),
         };
 
-        let new_group = Rc::new(Node::new_group(new_group_name));
-
-        parent_group.add(new_group).unwrap();
+        self.add(parent_group, Node::new_group(new_group_name, rank))?;
 
         Ok(())
     }
 
     // add a bird to the tree by name of group and parent
     pub fn add_bird(
-        &self,
+        &mut self,
         parent: &str,
         name: &str,
         scientific_name: &str,
     ) -> Result<(), GroupError> {
-        if name.len() < 1
+        if name.is_empty()
             || name.len() > 50
-            || scientific_name.len() < 1
+            || scientific_name.is_empty()
             || scientific_name.len() > 50
         {
             return Err(GroupError::InputOutsideOfBoundsError);
         }
-        let parent_group = match Self::get_group_with_name(Rc::clone(&self.root), parent) {
+        let parent_group = match self.get_group_with_name(parent) {
             Some(group) => group,
             None => return Err(GroupError::NoGroupExistsErr),
         };
 
-        let new_bird = Rc::new(Node::new_bird(name, scientific_name));
+        self.add(parent_group, Node::new_bird(name, scientific_name))?;
+
+        Ok(())
+    }
+
+    // remove a bird from the tree by its common name
+    pub fn remove_bird(&mut self, name: &str) -> Result<(), GroupError> {
+        let bird = match self.search_by_name(name) {
+            Some(bird) => bird,
+            None => return Err(GroupError::NoBirdExistsErr),
+        };
+
+        self.remove(bird, RemoveMode::EmptyOnly)?;
+
+        Ok(())
+    }
+
+    // remove a group from the tree by its name
+    pub fn remove_group(&mut self, group_name: &str, mode: RemoveMode) -> Result<(), GroupError> {
+        let group = match self.get_group_with_name(group_name) {
+            Some(group) => group,
+            None => return Err(GroupError::NoGroupExistsErr),
+        };
 
-        parent_group.add(new_bird).unwrap();
+        self.remove(group, mode)?;
 
         Ok(())
     }
 
-    /// convert data from file into nodes
-    pub fn insert_data(&mut self, data: &BirdData) {
-        let mut current_group = Rc::clone(&self.root);
+    // move a bird into a different group by its common name
+    pub fn move_bird(&mut self, name: &str, new_parent: &str) -> Result<(), GroupError> {
+        let bird = match self.search_by_name(name) {
+            Some(bird) => bird,
+            None => return Err(GroupError::NoBirdExistsErr),
+        };
+        let new_parent = match self.get_group_with_name(new_parent) {
+            Some(group) => group,
+            None => return Err(GroupError::NoGroupExistsErr),
+        };
+
+        self.move_node(bird, new_parent)
+    }
+
+    // move a group and everything inside it into a different group
+    pub fn move_group(&mut self, group_name: &str, new_parent: &str) -> Result<(), GroupError> {
+        let (Some(group), Some(new_parent)) = (
+            self.get_group_with_name(group_name),
+            self.get_group_with_name(new_parent),
+        ) else {
+            return Err(GroupError::NoGroupExistsErr);
+        };
+
+        self.move_node(group, new_parent)
+    }
+
+    // change the common name of a bird
+    pub fn rename_bird(&mut self, name: &str, new_name: &str) -> Result<(), GroupError> {
+        if new_name.is_empty() || new_name.len() > 50 {
+            return Err(GroupError::InputOutsideOfBoundsError);
+        }
+        let bird = match self.search_by_name(name) {
+            Some(bird) => bird,
+            None => return Err(GroupError::NoBirdExistsErr),
+        };
+
+        self.set_name(bird, new_name)
+    }
+
+    // change the scientific name of a bird found by its common name
+    pub fn rename_scientific_name(
+        &mut self,
+        name: &str,
+        new_scientific_name: &str,
+    ) -> Result<(), GroupError> {
+        if new_scientific_name.is_empty() || new_scientific_name.len() > 50 {
+            return Err(GroupError::InputOutsideOfBoundsError);
+        }
+        let bird = match self.search_by_name(name) {
+            Some(bird) => bird,
+            None => return Err(GroupError::NoBirdExistsErr),
+        };
+
+        self.set_scientific_name(bird, new_scientific_name)
+    }
+
+    // change the name of a group
+    pub fn rename_group(&mut self, group_name: &str, new_name: &str) -> Result<(), GroupError> {
+        if new_name.is_empty() || new_name.len() > 50 {
+            return Err(GroupError::InputOutsideOfBoundsError);
+        }
+        let group = match self.get_group_with_name(group_name) {
+            Some(group) => group,
+            None => return Err(GroupError::NoGroupExistsErr),
+        };
+
+        self.set_name(group, new_name)
+    }
+
+    /// Build a tree entirely from data loaded from a file.
+    /// The root of the tree is the group without any parent nodes, or if there isn't one, the
+    /// first parent node of the first bird. Groups and birds that can't be added, such as ones
+    /// reusing a name, are left out and returned along with the tree.
+    /// Returns None if there is nothing to find the root from.
+    pub fn from_data(
+        groups: &[GroupData],
+        birds: &[BirdData],
+    ) -> Option<(Self, Vec<RejectedData>)> {
+        let root = match groups.iter().find(|group| group.parent_nodes.is_empty()) {
+            Some(root) => Node::new_group(&root.name, root.rank),
+            None => {
+                let path = &birds.first()?.parent_nodes;
+                let rank = (path.len() == Rank::PRINCIPAL.len()).then_some(Rank::Kingdom);
+                Node::new_group(path.first()?, rank)
+            }
+        };
+
+        let mut tree = Self::new(root)?;
+        let mut rejected = vec![];
+        for (index, group) in groups.iter().enumerate() {
+            if group.parent_nodes.is_empty() {
+                continue;
+            }
+            let mut path = group.parent_nodes.clone();
+            path.push(group.name.clone());
+            let clash = tree.clashing_group(&path).map(str::to_string);
+            if let Err(error) = tree.insert_group(group) {
+                rejected.push(RejectedData {
+                    kind: "group",
+                    index,
+                    name: group.name.clone(),
+                    clash,
+                    error,
+                });
+            }
+        }
+        for (index, bird) in birds.iter().enumerate() {
+            if bird.parent_nodes.is_empty() {
+                continue;
+            }
+            let clash = tree.clashing_group(&bird.parent_nodes).map(str::to_string);
+            if let Err(error) = tree.insert_data(bird) {
+                rejected.push(RejectedData {
+                    kind: "bird",
+                    index,
+                    name: bird.common_name.clone(),
+                    clash,
+                    error,
+                });
+            }
+        }
+
+        Some((tree, rejected))
+    }
+
+    /// Find a group by following a path of group names, where the first name is the root,
+    /// creating any groups that don't exist yet. New groups get the rank at the same position in
+    /// `ranks`, unless that would break the order of ranks.
+    fn get_or_create_path(
+        &mut self,
+        path: &[String],
+        ranks: &[Option<Rank>],
+    ) -> Result<NodeId, GroupError> {
+        let mut current_group = self.root();
 
         // starting at index 1 to ignore the root node
-        for group_name in data.parent_nodes[1..].iter() {
-            if let Some(group) = Self::get_group_with_name(Rc::clone(&current_group), group_name) {
+        for (i, group_name) in path.iter().enumerate().skip(1) {
+            current_group = match self.get_child_group(current_group, group_name) {
                 // if the group exists, search in it's children instead
-                current_group = Rc::clone(&group);
-            } else {
+                Some(group) => group,
                 // if the group doesn't exist, create new groups
-                let new_group = Rc::new(Node::new_group(&group_name));
-                Rc::clone(&current_group)
-                    .add(Rc::clone(&new_group))
-                    .unwrap();
+                None => {
+                    // leave the group unranked rather than breaking the order of ranks
+                    let rank = ranks.get(i).copied().flatten().filter(|rank| {
+                        self.nearest_rank(current_group)
+                            .is_none_or(|parent_rank| parent_rank < *rank)
+                    });
+
+                    self.add(current_group, Node::new_group(group_name, rank))?
+                }
+            };
+        }
+
+        Ok(current_group)
+    }
+
+    /// Get the first group in a path of group names, where the first name is the root, that
+    /// `get_or_create_path` would fail to create because its name is already used by a group
+    /// somewhere else in the tree, or earlier in the path.
+    pub fn clashing_group<'a>(&self, path: &'a [String]) -> Option<&'a str> {
+        let mut current = Some(self.root());
+        let mut created = vec![];
+        for group_name in path.iter().skip(1) {
+            if let Some(group) = current.and_then(|group| self.get_child_group(group, group_name)) {
+                current = Some(group);
+                continue;
+            }
 
-                current_group = new_group
+            // every group from here on would be created
+            current = None;
+            let name = normalize(group_name);
+            if self.groups_by_name.contains_key(&name) || created.contains(&name) {
+                return Some(group_name);
             }
+            created.push(name);
         }
 
-        // add the final parent as a direct parent
-        self.direct_parents.push(Rc::clone(&current_group));
+        None
+    }
+
+    /// convert group data from file into a group node
+    pub fn insert_group(&mut self, data: &GroupData) -> Result<NodeId, GroupError> {
+        let mut path = data.parent_nodes.clone();
+        path.push(data.name.clone());
+
+        let mut ranks = vec![None; data.parent_nodes.len()];
+        ranks.push(data.rank);
+
+        self.get_or_create_path(&path, &ranks)
+    }
+
+    /// convert data from file into nodes
+    pub fn insert_data(&mut self, data: &BirdData) -> Result<NodeId, GroupError> {
+        // the file doesn't store ranks, but a full path of principal ranks can be recognised
+        let ranks = if data.parent_nodes.len() == Rank::PRINCIPAL.len() {
+            Rank::PRINCIPAL.map(Some)
+        } else {
+            [None; Rank::PRINCIPAL.len()]
+        };
+
+        self.insert_data_with_ranks(data, &ranks)
+    }
+
+    /// convert data from file into nodes, where any groups that need creating get the rank at
+    /// the same position in `ranks`
+    pub fn insert_data_with_ranks(
+        &mut self,
+        data: &BirdData,
+        ranks: &[Option<Rank>],
+    ) -> Result<NodeId, GroupError> {
+        let group = self.get_or_create_path(&data.parent_nodes, ranks)?;
 
         // add a bird to the final group
-        let bird = Rc::new(Node::new_bird(&data.common_name, &data.name));
-        current_group.add(bird).unwrap();
+        self.add(group, Node::new_bird(&data.common_name, &data.name))
     }
 }
 
-/// Build a hardcoded tree of birds
-pub fn build_tree() -> BirdTree {
-    // create bird groups
-    let animalia = Rc::new(Node::new_group("Animalia"));
-    let chordata = Rc::new(Node::new_group("Chordata"));
-    let aves = Rc::new(Node::new_group("Aves"));
-    let psittiaciformes = Rc::new(Node::new_group("Psittiaciformes"));
-    let apterygiformes = Rc::new(Node::new_group("Apterygiformes"));
-    let passeriformes = Rc::new(Node::new_group("Passeriformes"));
-    let strigopidae = Rc::new(Node::new_group("Strigopidae"));
-    let apterygidae = Rc::new(Node::new_group("Apterygidae"));
-    let rhipiduridae = Rc::new(Node::new_group("Rhipiduridae"));
-    let meliphagidae = Rc::new(Node::new_group("Meliphagidae"));
-    let nestor = Rc::new(Node::new_group("Nestor"));
-    let apteryx = Rc::new(Node::new_group("Apteryx"));
-    let rhipidura = Rc::new(Node::new_group("Rhipidura"));
-    let prosthemadera = Rc::new(Node::new_group("Prosthemadera"));
-
-    // organise tree of groups
-    Rc::clone(&animalia).add(Rc::clone(&chordata)).unwrap();
-    Rc::clone(&chordata).add(Rc::clone(&aves)).unwrap();
-    Rc::clone(&aves).add(Rc::clone(&psittiaciformes)).unwrap();
-    Rc::clone(&aves).add(Rc::clone(&apterygiformes)).unwrap();
-    Rc::clone(&aves).add(Rc::clone(&passeriformes)).unwrap();
-    Rc::clone(&psittiaciformes)
-        .add(Rc::clone(&strigopidae))
-        .unwrap();
-    Rc::clone(&apterygiformes)
-        .add(Rc::clone(&apterygidae))
-        .unwrap();
-    Rc::clone(&passeriformes)
-        .add(Rc::clone(&rhipiduridae))
-        .unwrap();
-    Rc::clone(&passeriformes)
-        .add(Rc::clone(&meliphagidae))
-        .unwrap();
-    Rc::clone(&strigopidae).add(Rc::clone(&nestor)).unwrap();
-    Rc::clone(&apterygidae).add(Rc::clone(&apteryx)).unwrap();
-    Rc::clone(&rhipiduridae).add(Rc::clone(&rhipidura)).unwrap();
-    Rc::clone(&meliphagidae)
-        .add(Rc::clone(&prosthemadera))
-        .unwrap();
-
-    // add birds to groups
-    Rc::clone(&nestor)
-        .add(Rc::new(Node::new_bird("Kaka", "meridionalis")))
-        .unwrap();
-    Rc::clone(&nestor)
-        .add(Rc::new(Node::new_bird("Kea", "notabilis")))
-        .unwrap();
-    Rc::clone(&apteryx)
-        .add(Rc::new(Node::new_bird("Little Spotted Kiwi", "owenii")))
-        .unwrap();
-    Rc::clone(&rhipidura)
-        .add(Rc::new(Node::new_bird("Piwakawaka", "fuliginosa")))
-        .unwrap();
-    Rc::clone(&prosthemadera)
-        .add(Rc::new(Node::new_bird("Tui", "novaeseelandiea")))
-        .unwrap();
-
-    let tree = BirdTree::new(
-        animalia,
-        vec![
-            nestor.clone(),
-            apteryx.clone(),
-            rhipidura.clone(),
-            prosthemadera.clone(),
-        ],
-    )
-    .expect("Didn't put in invalid values");
-
-    tree
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file::{load_tree, save_tree, seed_tree};
+    use crate::testing::TestDir;
+
+    /// Get the binomial name of a bird found by its common name.
+    fn binomial(tree: &BirdTree, name: &str) -> String {
+        let bird = tree.search_by_name(name).unwrap();
+        tree.binomial_name(bird).unwrap().to_string()
+    }
+
+    /// Serialize the seed tree, change it with `change`, and deserialize it again.
+    fn reload(change: impl FnOnce(&mut serde_json::Value)) -> Result<BirdTree, serde_json::Error> {
+        let mut value = serde_json::to_value(seed_tree()).unwrap();
+        change(&mut value);
+        serde_json::from_value(value)
+    }
+
+    /// Get the id of a node by its name, as it is in the serialized seed tree.
+    fn seed_id(name: &str) -> usize {
+        let tree = seed_tree();
+        let id = tree
+            .get_group_with_name(name)
+            .or_else(|| tree.search_by_name(name))
+            .unwrap();
+        id.0
+    }
+
+    #[test]
+    fn trees_serialize_directly() {
+        let seed = seed_tree();
+
+        let tree = reload(|_| {}).unwrap();
+
+        assert_eq!(tree.root(), seed.root());
+        let kea = tree.search_by_scientific_name("Nestor notabilis").unwrap();
+        assert_eq!(Some(kea), seed.search_by_name("Kea"));
+        assert_eq!(tree.preorder(tree.root()), seed.preorder(seed.root()));
+    }
+
+    #[test]
+    fn serialized_trees_must_be_linked_correctly() {
+        let (nestor, kea) = (seed_id("Nestor"), seed_id("Kea"));
+
+        // a child that doesn't point back at its parent
+        assert!(reload(|value| value["nodes"][kea]["parent"] = 0.into()).is_err());
+        // a link to a node that isn't there
+        assert!(
+            reload(|value| value["nodes"][nestor]["children"] = serde_json::json!([999])).is_err()
+        );
+        // a group that contains itself
+        assert!(
+            reload(|value| {
+                value["nodes"][nestor]["parent"] = nestor.into();
+                value["nodes"][nestor]["children"]
+                    .as_array_mut()
+                    .unwrap()
+                    .push(nestor.into());
+            })
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn serialized_trees_must_follow_the_rules_of_add() {
+        let (nestor, kea) = (seed_id("Nestor"), seed_id("Kea"));
+
+        assert!(
+            reload(|value| value["nodes"][kea]["node"]["Bird"]["name"] = "Kaka".into()).is_err()
+        );
+        assert!(
+            reload(|value| value["nodes"][nestor]["node"]["Group"]["rank"] = "order".into())
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn removed_birds_leave_the_indexes() {
+        let mut tree = seed_tree();
+        let nestor = tree.get_group_with_name("Nestor").unwrap();
+
+        tree.remove_bird("kea").unwrap();
+
+        assert!(tree.search_by_name("Kea").is_none());
+        assert!(tree.search_by_scientific_name("Nestor notabilis").is_none());
+        assert!(tree.search_by_scientific_name("notabilis").is_none());
+        assert_eq!(tree.count_birds(nestor), 1);
+        assert!(matches!(
+            tree.remove_bird("Kea"),
+            Err(GroupError::NoBirdExistsErr)
+        ));
+    }
+
+    #[test]
+    fn groups_with_children_are_only_removed_with_cascade() {
+        let mut tree = seed_tree();
+        let birds = tree.count_birds(tree.root());
+
+        assert!(matches!(
+            tree.remove_group("Strigopidae", RemoveMode::EmptyOnly),
+            Err(GroupError::GroupNotEmptyError)
+        ));
+        assert!(tree.search_by_name("Kea").is_some());
+
+        tree.remove_group("Strigopidae", RemoveMode::Cascade)
+            .unwrap();
+
+        assert_eq!(tree.count_birds(tree.root()), birds - 2);
+        assert!(tree.get_group_with_name("Nestor").is_none());
+        assert!(tree.search_by_name("Kaka").is_none());
+        // the names are free to use again
+        tree.add_group("Psittaciformes", "Nestor", Some(Rank::Genus))
+            .unwrap();
+        tree.add_bird("Nestor", "Kea", "notabilis").unwrap();
+    }
+
+    #[test]
+    fn the_root_can_not_be_removed_or_moved() {
+        let mut tree = seed_tree();
+        let root = tree.root();
+        let aves = tree.get_group_with_name("Aves").unwrap();
+
+        assert!(matches!(
+            tree.remove(root, RemoveMode::Cascade),
+            Err(GroupError::RootNodeError)
+        ));
+        assert!(matches!(
+            tree.move_node(root, aves),
+            Err(GroupError::RootNodeError)
+        ));
+    }
+
+    #[test]
+    fn moved_groups_take_their_birds_with_them() {
+        let mut tree = seed_tree();
+
+        tree.move_group("Nestor", "Apterygidae").unwrap();
+
+        let nestor = tree.get_group_with_name("Nestor").unwrap();
+        assert_eq!(tree.parent(nestor), tree.get_group_with_name("Apterygidae"));
+        assert_eq!(tree.count_birds(nestor), 2);
+        let kea = tree.search_by_scientific_name("Nestor notabilis").unwrap();
+        assert_eq!(
+            tree.classification_path(kea),
+            Some(
+                "Animalia > Chordata > Aves > Apterygiformes > Apterygidae > Nestor > notabilis"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn moves_inside_themselves_are_rejected() {
+        let mut tree = seed_tree();
+
+        assert!(matches!(
+            tree.move_group("Nestor", "Nestor"),
+            Err(GroupError::CycleError)
+        ));
+        assert!(matches!(
+            tree.move_group("Psittaciformes", "Nestor"),
+            Err(GroupError::CycleError)
+        ));
+    }
+
+    #[test]
+    fn moves_and_adds_keep_ranks_in_order() {
+        let mut tree = seed_tree();
+
+        assert!(matches!(
+            tree.move_group("Apterygidae", "Nestor"),
+            Err(GroupError::RankOrderError {
+                parent: Rank::Genus,
+                child: Rank::Family
+            })
+        ));
+        assert!(matches!(
+            tree.add_group("Nestor", "Psittacidae", Some(Rank::Family)),
+            Err(GroupError::RankOrderError { .. })
+        ));
+        // unranked groups can go anywhere
+        tree.add_group("Nestor", "Mountain parrots", None).unwrap();
+    }
+
+    #[test]
+    fn birds_can_not_hold_anything() {
+        let mut tree = seed_tree();
+        let (kea, kaka) = (
+            tree.search_by_name("Kea").unwrap(),
+            tree.search_by_name("Kaka").unwrap(),
+        );
+
+        assert!(matches!(
+            tree.move_node(kea, kaka),
+            Err(GroupError::NodeTypeError)
+        ));
+        assert!(matches!(
+            tree.add(kaka, Node::new_bird("Chick", "pullus")),
+            Err(GroupError::NodeTypeError)
+        ));
+    }
+
+    #[test]
+    fn adds_reject_names_in_use() {
+        let mut tree = seed_tree();
+
+        assert!(matches!(
+            tree.add_group("Aves", " nestor ", None),
+            Err(GroupError::DuplicateNameError)
+        ));
+        assert!(matches!(
+            tree.add_bird("Apteryx", "KEA", "mantelli"),
+            Err(GroupError::DuplicateNameError)
+        ));
+        assert!(matches!(
+            tree.add_bird("Nestor", "Kākā", "meridionalis"),
+            Err(GroupError::DuplicateNameError)
+        ));
+        // species epithets only need to be unique within their genus
+        tree.add_bird("Apteryx", "Kiwi notabilis", "notabilis")
+            .unwrap();
+        assert!(matches!(
+            tree.add_group("Aves", &"x".repeat(51), None),
+            Err(GroupError::InputOutsideOfBoundsError)
+        ));
+    }
+
+    #[test]
+    fn renames_reject_names_in_use() {
+        let mut tree = seed_tree();
+
+        assert!(matches!(
+            tree.rename_bird("Kea", "kaka"),
+            Err(GroupError::DuplicateNameError)
+        ));
+        assert!(matches!(
+            tree.rename_group("Nestor", "Apteryx"),
+            Err(GroupError::DuplicateNameError)
+        ));
+        assert!(matches!(
+            tree.rename_scientific_name("Kea", "meridionalis"),
+            Err(GroupError::DuplicateNameError)
+        ));
+
+        // a node can keep its own name with different casing
+        tree.rename_bird("Kea", "KEA").unwrap();
+        tree.rename_scientific_name("KEA", "owenii").unwrap();
+        assert_eq!(binomial(&tree, "kea"), "Nestor owenii");
+    }
+
+    #[test]
+    fn renamed_genera_rename_their_birds() {
+        let mut tree = seed_tree();
+
+        tree.rename_group("Nestor", "Nestorius").unwrap();
+
+        assert!(tree.search_by_scientific_name("Nestor notabilis").is_none());
+        assert_eq!(
+            tree.search_by_scientific_name("nestorius notabilis"),
+            tree.search_by_name("Kea")
+        );
+    }
+
+    #[test]
+    fn moves_into_a_genus_with_the_same_epithet_are_rejected() {
+        let mut tree = seed_tree();
+        tree.add_group("Strigopidae", "Foo", Some(Rank::Genus))
+            .unwrap();
+        tree.add_bird("Foo", "X", "notabilis").unwrap();
+
+        assert!(matches!(
+            tree.move_bird("X", "Nestor"),
+            Err(GroupError::DuplicateNameError)
+        ));
+        assert_eq!(binomial(&tree, "X"), "Foo notabilis");
+        assert_eq!(binomial(&tree, "Kea"), "Nestor notabilis");
+    }
+
+    #[test]
+    fn moved_nodes_are_saved_and_load_again() {
+        let dir = TestDir::new("moved_nodes_are_saved_and_load_again");
+        let path = dir.path("birdData.json");
+        let mut tree = seed_tree();
+        tree.add_group("Strigopidae", "Foo", Some(Rank::Genus))
+            .unwrap();
+        tree.add_bird("Foo", "X", "notabilis").unwrap();
+
+        tree.move_bird("Kaka", "Foo").unwrap();
+        tree.move_group("Foo", "Psittaciformes").unwrap();
+        save_tree(&tree, &path, 0).unwrap();
+        let loaded = load_tree(&path).unwrap();
+
+        assert_eq!(binomial(&loaded, "Kaka"), "Foo meridionalis");
+        assert_eq!(binomial(&loaded, "X"), "Foo notabilis");
+        let foo = loaded.get_group_with_name("Foo").unwrap();
+        let parent = loaded.parent(foo).unwrap();
+        assert_eq!(loaded.get(parent).unwrap().name(), "Psittaciformes");
+    }
 }
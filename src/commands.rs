@@ -0,0 +1,962 @@
+use std::fs;
+use std::io::{self, Write, stdout};
+use std::path::Path;
+use std::process::ExitCode;
+
+use crate::birds::{BirdTree, Rank, RemoveMode};
+use crate::config::Config;
+use crate::diagram::{self, TextTreeOptions};
+use crate::file::{self, CsvField, FileFormat};
+use crate::newick;
+use crate::output::{self, OutputFormat};
+use crate::ui::{
+    self, add_bird_error_message, add_group_error_message, group_error_message, print_bird,
+    suggest_birds, supports_unicode,
+};
+
+/// Why a command failed.
+enum Failure {
+    /// The arguments don't make sense for the command. Exits with code 2.
+    Usage(String),
+    /// The command could not do what was asked. Exits with code 1.
+    Error(String),
+}
+
+/// The arguments given to a command, split into positional arguments and flags.
+struct Args<'a> {
+    positional: Vec<&'a str>,
+    values: Vec<(&'a str, &'a str)>,
+    switches: Vec<&'a str>,
+}
+
+impl<'a> Args<'a> {
+    /// Split the arguments of a command, given the flags that take a value and the flags that
+    /// don't. Flags can be written as `--flag value` or `--flag=value`, and everything after
+    /// `--` is a positional argument.
+    fn parse(value_flags: &[&str], switches: &[&str], args: &[&'a str]) -> Result<Self, Failure> {
+        let mut parsed = Self {
+            positional: vec![],
+            values: vec![],
+            switches: vec![],
+        };
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            if *arg == "--" {
+                parsed.positional.extend(args.by_ref());
+                break;
+            }
+            if !arg.starts_with("--") {
+                parsed.positional.push(arg);
+                continue;
+            }
+
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) => (flag, Some(value)),
+                None => (*arg, None),
+            };
+            if value_flags.contains(&flag) {
+                let value = inline_value
+                    .or_else(|| args.next().copied())
+                    .ok_or_else(|| Failure::Usage(format!("{} needs a value", flag)))?;
+                parsed.values.push((flag, value));
+            } else if switches.contains(&flag) && inline_value.is_none() {
+                parsed.switches.push(flag);
+            } else {
+                return Err(Failure::Usage(format!("unknown option: {}", arg)));
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    /// Get the value of a flag. The last value wins if the flag was given more than once.
+    fn value(&self, flag: &str) -> Option<&'a str> {
+        self.values
+            .iter()
+            .rev()
+            .find(|(other, _)| *other == flag)
+            .map(|(_, value)| *value)
+    }
+
+    fn switch(&self, flag: &str) -> bool {
+        self.switches.contains(&flag)
+    }
+
+    /// Get the positional arguments, checking that there are between `min` and `max` of them.
+    fn positional(&self, min: usize, max: usize) -> Result<&[&'a str], Failure> {
+        match self.positional.len() {
+            len if len < min => Err(Failure::Usage("not enough arguments".to_string())),
+            len if len > max => Err(Failure::Usage(format!(
+                "unexpected argument: {}",
+                self.positional[max]
+            ))),
+            _ => Ok(&self.positional),
+        }
+    }
+}
+
+/// A command that can be run from the command line instead of the menu.
+struct Command {
+    name: &'static str,
+    /// The arguments the command takes
+    usage: &'static str,
+    /// A one line description of the command
+    about: &'static str,
+    /// More about the arguments, shown in the help for the command
+    details: &'static str,
+    /// Flags that take a value, e.g. `--depth 2`
+    value_flags: &'static [&'static str],
+    /// Flags that are either given or not, e.g. `--counts`
+    switches: &'static [&'static str],
+    run: fn(&Config, &Args) -> Result<(), Failure>,
+}
+
+/// Every command, in the order they are shown in the help.
+const COMMANDS: [Command; 11] = [
+    Command {
+        name: "search",
+        usage: "search (--name <name> | --scientific <name>) [--format <text|json|tsv>]",
+        about: "Show a bird found by its common or scientific name",
+        details: "  --format <format>  Print the results as text, json records, or tsv with a header row",
+        value_flags: &["--name", "--scientific", "--format"],
+        switches: &[],
+        run: search,
+    },
+    Command {
+        name: "list-group",
+        usage: "list-group <group> [--format <text|json|tsv>]",
+        about: "Show every bird in a group",
+        details: "  --format <format>  Print the results as text, json records, or tsv with a header row",
+        value_flags: &["--format"],
+        switches: &[],
+        run: list_group,
+    },
+    Command {
+        name: "tree",
+        usage: "tree [<group>] [--depth <levels>] [--counts] [--ascii | --unicode] \
+                [--format <text|json|tsv>]",
+        about: "Show the tree, or a group, as indented text",
+        details: "  --depth <levels>   Only show this many levels below the group
+  --counts           Show how many species are in each group
+  --ascii            Draw the lines with ASCII characters
+  --unicode          Draw the lines with box drawing characters
+  --format <format>  Print the tree as text, nested json records, or tsv with a row per node",
+        value_flags: &["--depth", "--format"],
+        switches: &["--counts", "--ascii", "--unicode"],
+        run: tree,
+    },
+    Command {
+        name: "add-group",
+        usage: "add-group <parent> <name> [--rank <rank>]",
+        about: "Add a classification inside another one",
+        details: "  --rank <rank>  The taxonomic rank of the group, e.g. order, family or genus",
+        value_flags: &["--rank"],
+        switches: &[],
+        run: add_group,
+    },
+    Command {
+        name: "add-bird",
+        usage: "add-bird <parent> <common name> <scientific name>",
+        about: "Add a species to a classification",
+        details: "",
+        value_flags: &[],
+        switches: &[],
+        run: add_bird,
+    },
+    Command {
+        name: "batch",
+        usage: "batch [<script>] [--atomic]",
+        about: "Make the changes in a script, one command per line",
+        details: "  <script>  The script to read, or - to read standard input (the default)
+  --atomic  Save nothing unless every line succeeds
+
+Each line is one of:
+  add-group <parent> <name> [--rank <rank>]
+  add-bird <parent> <common name> <scientific name>
+  remove-bird <common name>
+  remove-group <group> [--cascade]
+  move-bird <common name> <new parent>
+  move-group <group> <new parent>
+  rename-bird <common name> <new common name>
+  rename-scientific <common name> <new scientific name>
+  rename-group <group> <new name>
+
+Quote names that have spaces, e.g. add-bird Nestor \"Kākā\" meridionalis.
+Blank lines and lines starting with # are skipped.",
+        value_flags: &[],
+        switches: &["--atomic"],
+        run: batch,
+    },
+    Command {
+        name: "export",
+        usage: "export <csv|newick|nexus|dot|mermaid> <file> [<group>] [--label-groups]",
+        about: "Write the tree to a file in another format",
+        details: "  <file>          The file to write, or - to write to standard output
+  <group>         Only draw this group and everything in it (dot and mermaid only)
+  --label-groups  Label the groups as well as the birds (newick and nexus only)",
+        value_flags: &[],
+        switches: &["--label-groups"],
+        run: export,
+    },
+    Command {
+        name: "import",
+        usage: "import <csv|newick> <file> [<header>=<column>...]",
+        about: "Add the species in a CSV file, or replace the tree with a Newick file",
+        details: "  <header>=<column>  Read a CSV column as a rank, scientificName or commonName
+                     when its header isn't recognised",
+        value_flags: &[],
+        switches: &[],
+        run: import,
+    },
+    Command {
+        name: "convert",
+        usage: "convert <flat|nested> [<output>]",
+        about: "Rewrite the data file in another json layout",
+        details: "  <output>  Write to a new file instead of replacing the data file",
+        value_flags: &[],
+        switches: &[],
+        run: convert,
+    },
+    Command {
+        name: "backups",
+        usage: "backups",
+        about: "List the backups of the data file, oldest first",
+        details: "",
+        value_flags: &[],
+        switches: &[],
+        run: backups,
+    },
+    Command {
+        name: "restore",
+        usage: "restore <backup>",
+        about: "Replace the data file with one of its backups",
+        details: "",
+        value_flags: &[],
+        switches: &[],
+        run: restore,
+    },
+];
+
+/// Print the help for the whole program.
+fn print_help() {
+    println!("Usage: formative [--data <path>] [--backups <count>] [<command> [<args>...]]");
+    println!();
+    println!("Track the birds of Zealandia. Without a command, an interactive menu is shown.");
+    println!();
+    println!("Options:");
+    println!("  --data <path>      The data file to use, instead of $ZEALANDIA_DATA or the config");
+    println!("  --backups <count>  How many backups of the data file to keep when saving");
+    println!("  -h, --help         Show this help, or the help for a command");
+    println!();
+    println!("Commands:");
+    for command in COMMANDS.iter() {
+        println!("  {:<12}{}", command.name, command.about);
+    }
+    println!();
+    println!("Exit codes: 0 on success, 1 if the command failed, 2 for invalid arguments");
+}
+
+/// Print the help for a single command.
+fn print_command_help(command: &Command) {
+    println!("Usage: formative {}", command.usage);
+    println!();
+    println!("{}", command.about);
+    if !command.details.is_empty() {
+        println!();
+        println!("{}", command.details);
+    }
+}
+
+/// Run a command from its arguments, where the first argument is the name of the command.
+/// Returns None if there is no command, so that the menu should be shown instead.
+pub fn run(config: &Config, args: &[&str]) -> Option<ExitCode> {
+    let (name, args) = args.split_first()?;
+
+    if ["-h", "--help", "help"].contains(name) {
+        match args.first().and_then(|name| find_command(name)) {
+            Some(command) => print_command_help(command),
+            None => print_help(),
+        }
+        return Some(ExitCode::SUCCESS);
+    }
+
+    let Some(command) = find_command(name) else {
+        eprintln!("Error: unknown command: {}", name);
+        eprintln!("Run formative --help to see every command");
+        return Some(ExitCode::from(2));
+    };
+    if args.iter().any(|arg| ["-h", "--help"].contains(arg)) {
+        print_command_help(command);
+        return Some(ExitCode::SUCCESS);
+    }
+
+    let result = Args::parse(command.value_flags, command.switches, args)
+        .and_then(|args| (command.run)(config, &args));
+    Some(match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(Failure::Usage(message)) => {
+            eprintln!("Error: {}", message);
+            eprintln!("Usage: formative {}", command.usage);
+            ExitCode::from(2)
+        }
+        Err(Failure::Error(message)) => {
+            eprintln!("Error: {}", message);
+            ExitCode::FAILURE
+        }
+    })
+}
+
+fn find_command(name: &str) -> Option<&'static Command> {
+    COMMANDS.iter().find(|command| command.name == name)
+}
+
+/// Load the tree from the data file for a command, or the built in taxonomy when the default
+/// data file doesn't exist yet. Commands never ask how to recover a data file that can't be
+/// loaded, so that scripts don't wait for an answer. The menu offers that instead.
+fn load(config: &Config) -> Result<BirdTree, Failure> {
+    ui::load_tree(config, false).map_err(|e| {
+        Failure::Error(format!(
+            "{}\nRun formative without a command to recover the data file",
+            e
+        ))
+    })
+}
+
+/// Save the tree to the data file after a command has changed it.
+fn save(config: &Config, tree: &BirdTree) -> Result<(), Failure> {
+    file::save_tree(tree, &config.data_path, config.backup_count).map_err(|e| {
+        Failure::Error(format!(
+            "could not save to {}: {}",
+            config.data_path.display(),
+            e
+        ))
+    })
+}
+
+/// Get the format that a command should print its results in, which is text unless `--format` was
+/// given.
+fn output_format(args: &Args) -> Result<OutputFormat, Failure> {
+    match args.value("--format") {
+        Some(format) => format.parse::<OutputFormat>().map_err(|_| {
+            Failure::Usage(format!(
+                "{} is not an output format, use one of: {}",
+                format,
+                OutputFormat::ALL.map(|format| format.as_str()).join(", ")
+            ))
+        }),
+        None => Ok(OutputFormat::Text),
+    }
+}
+
+/// Write text to a file, or to standard output if the file is `-`.
+fn write_output(output: &str, text: &str) -> Result<(), Failure> {
+    let result = if output == "-" {
+        stdout().write_all(text.as_bytes())
+    } else {
+        fs::write(output, text)
+    };
+
+    result.map_err(|e| Failure::Error(format!("could not write {}: {}", output, e)))
+}
+
+fn search(config: &Config, args: &Args) -> Result<(), Failure> {
+    args.positional(0, 0)?;
+    let format = output_format(args)?;
+    let tree = load(config)?;
+
+    let (bird, name, not_found) = match (args.value("--name"), args.value("--scientific")) {
+        (Some(name), None) => (tree.search_by_name(name), name, "could not find bird"),
+        (None, Some(name)) => (
+            tree.search_by_scientific_name(name),
+            name,
+            "could not find bird with scientific name",
+        ),
+        _ => {
+            return Err(Failure::Usage(
+                "give either --name or --scientific".to_string(),
+            ));
+        }
+    };
+    let bird = bird.ok_or_else(|| {
+        Failure::Error(match suggest_birds(&tree, name) {
+            Some(suggestion) => format!("{}: {}\n{}", not_found, name, suggestion),
+            None => format!("{}: {}", not_found, name),
+        })
+    })?;
+
+    match format {
+        OutputFormat::Text => print_bird(&tree, bird),
+        OutputFormat::Json => print!("{}", output::bird_to_json(&tree, bird)),
+        OutputFormat::Tsv => print!("{}", output::birds_to_tsv(&tree, &[bird])),
+    }
+
+    Ok(())
+}
+
+fn list_group(config: &Config, args: &Args) -> Result<(), Failure> {
+    let group_name = args.positional(1, 1)?[0];
+    let format = output_format(args)?;
+    let tree = load(config)?;
+
+    let birds = tree
+        .birds_in_group_from_name(group_name)
+        .map_err(|e| Failure::Error(group_error_message(e, group_name)))?;
+    match format {
+        OutputFormat::Text => {
+            for bird in birds.iter() {
+                print_bird(&tree, *bird);
+            }
+        }
+        OutputFormat::Json => print!("{}", output::birds_to_json(&tree, &birds)),
+        OutputFormat::Tsv => print!("{}", output::birds_to_tsv(&tree, &birds)),
+    }
+
+    Ok(())
+}
+
+fn tree(config: &Config, args: &Args) -> Result<(), Failure> {
+    let group = args.positional(0, 1)?.first();
+    let format = output_format(args)?;
+    let max_depth = match args.value("--depth") {
+        Some(depth) => Some(
+            depth
+                .parse::<usize>()
+                .map_err(|_| Failure::Usage("--depth needs a number".to_string()))?,
+        ),
+        None => None,
+    };
+    let options = TextTreeOptions {
+        max_depth,
+        counts: args.switch("--counts"),
+        unicode: !args.switch("--ascii") && (args.switch("--unicode") || supports_unicode()),
+    };
+
+    let tree = load(config)?;
+    let start = match group {
+        Some(group) => tree
+            .get_group_with_name(group)
+            .ok_or_else(|| Failure::Error(format!("there is no group with name: {}", group)))?,
+        None => tree.root(),
+    };
+    let text = match format {
+        OutputFormat::Text => diagram::to_text_tree(&tree, start, &options),
+        OutputFormat::Json => output::tree_to_json(&tree, start, max_depth, options.counts),
+        OutputFormat::Tsv => output::tree_to_tsv(&tree, start, max_depth, options.counts),
+    };
+    print!("{}", text);
+
+    Ok(())
+}
+
+/// Load the tree, make a change to it and save it again, printing what was changed.
+fn modify(config: &Config, args: &Args, change: Change) -> Result<(), Failure> {
+    let mut tree = load(config)?;
+    let message = change(&mut tree, args)?;
+    save(config, &tree)?;
+    println!("{}", message);
+
+    Ok(())
+}
+
+fn add_group(config: &Config, args: &Args) -> Result<(), Failure> {
+    modify(config, args, change_add_group)
+}
+
+fn add_bird(config: &Config, args: &Args) -> Result<(), Failure> {
+    modify(config, args, change_add_bird)
+}
+
+fn export(config: &Config, args: &Args) -> Result<(), Failure> {
+    let (format, output, group) = match args.positional(2, 3)? {
+        [format, output] => (*format, *output, None),
+        [format, output, group] => (*format, *output, Some(*group)),
+        _ => unreachable!(),
+    };
+    let label_groups = args.switch("--label-groups");
+    if group.is_some() && !["dot", "mermaid"].contains(&format) {
+        return Err(Failure::Usage(
+            "only dot and mermaid exports can start at a group".to_string(),
+        ));
+    }
+    if label_groups && !["newick", "nexus"].contains(&format) {
+        return Err(Failure::Usage(
+            "--label-groups only applies to newick and nexus exports".to_string(),
+        ));
+    }
+
+    let tree = load(config)?;
+    let start = match group {
+        Some(group) => tree
+            .get_group_with_name(group)
+            .ok_or_else(|| Failure::Error(format!("there is no group with name: {}", group)))?,
+        None => tree.root(),
+    };
+    let text = match format {
+        "csv" => file::to_csv(&tree),
+        "newick" => newick::to_newick(&tree, label_groups) + "\n",
+        "nexus" => newick::to_nexus(&tree, label_groups),
+        "dot" => diagram::to_dot(&tree, start),
+        "mermaid" => diagram::to_mermaid(&tree, start),
+        _ => {
+            return Err(Failure::Usage(format!(
+                "{} is not an export format, use csv, newick, nexus, dot or mermaid",
+                format
+            )));
+        }
+    };
+
+    write_output(output, &text)?;
+    if output != "-" {
+        println!("Exported the tree to {}", output);
+    }
+
+    Ok(())
+}
+
+fn import(config: &Config, args: &Args) -> Result<(), Failure> {
+    let (format, input, mappings) = match args.positional(2, usize::MAX)? {
+        [format, input, mappings @ ..] => (*format, *input, mappings),
+        _ => unreachable!(),
+    };
+
+    match format {
+        "csv" => import_csv(config, input, mappings),
+        "newick" if mappings.is_empty() => import_newick(config, input),
+        "newick" => Err(Failure::Usage(
+            "column mappings only apply to csv imports".to_string(),
+        )),
+        _ => Err(Failure::Usage(format!(
+            "{} is not an import format, use csv or newick",
+            format
+        ))),
+    }
+}
+
+/// Add the species in a CSV file to the data file, reporting any rows that couldn't be placed.
+/// Each mapping is `<header>=<column>`, naming the column a header holds.
+fn import_csv(config: &Config, csv: &str, mappings: &[&str]) -> Result<(), Failure> {
+    let mut mapping = vec![];
+    for pair in mappings.iter() {
+        let (header, field) = pair
+            .split_once('=')
+            .ok_or_else(|| Failure::Usage(format!("{} should be <header>=<column>", pair)))?;
+        let field = field.parse::<CsvField>().map_err(|_| {
+            Failure::Usage(format!(
+                "{} is not a column, use a rank, scientificName or commonName",
+                field
+            ))
+        })?;
+        mapping.push((header.to_string(), field));
+    }
+
+    let mut tree = load(config)?;
+    let import = file::import_csv(&mut tree, Path::new(csv), &mapping)
+        .map_err(|e| Failure::Error(e.to_string()))?;
+
+    if !import.ignored_columns.is_empty() {
+        println!("Ignored columns: {}", import.ignored_columns.join(", "));
+    }
+    for row in import.rejected.iter() {
+        println!("Line {}: {}", row.line, row.reason);
+    }
+    println!(
+        "Imported {} birds, {} rows could not be placed",
+        import.imported,
+        import.rejected.len()
+    );
+
+    if import.imported > 0 {
+        save(config, &tree)?;
+    }
+
+    match import.rejected.len() {
+        0 => Ok(()),
+        rejected => Err(Failure::Error(format!(
+            "{} rows of {} could not be placed",
+            rejected, csv
+        ))),
+    }
+}
+
+/// Replace the birds in the data file with a tree read from a Newick or NEXUS file.
+/// The data file being replaced becomes a backup so that it can be restored, which means this
+/// is refused when no backups are kept.
+fn import_newick(config: &Config, input: &str) -> Result<(), Failure> {
+    if config.backup_count == 0 && config.data_path.exists() {
+        return Err(Failure::Error(format!(
+            "a newick import replaces every bird in {}, so it needs --backups of at least 1",
+            config.data_path.display()
+        )));
+    }
+    let tree = newick::read_newick(Path::new(input)).map_err(|e| Failure::Error(e.to_string()))?;
+
+    save(config, &tree)?;
+    println!(
+        "Replaced {} with the tree in {}",
+        config.data_path.display(),
+        input
+    );
+
+    Ok(())
+}
+
+/// Rewrite the data file in another format, either in place or to a new file.
+fn convert(config: &Config, args: &Args) -> Result<(), Failure> {
+    let (format, output) = match args.positional(1, 2)? {
+        [format] => (*format, None),
+        [format, output] => (*format, Some(*output)),
+        _ => unreachable!(),
+    };
+    let format = format.parse::<FileFormat>().map_err(|_| {
+        Failure::Usage(format!(
+            "{} is not a file format, use one of: {}",
+            format,
+            FileFormat::ALL.map(|format| format.as_str()).join(", ")
+        ))
+    })?;
+
+    let tree = file::load_tree(&config.data_path).map_err(|e| Failure::Error(e.to_string()))?;
+
+    // only back up the data file when it is being replaced
+    let (path, keep_backups) = match output {
+        Some(output) => (Path::new(output), 0),
+        None => (config.data_path.as_path(), config.backup_count),
+    };
+    file::save_tree_as(&tree, path, keep_backups, format)
+        .map_err(|e| Failure::Error(format!("could not convert the data file: {}", e)))?;
+    println!(
+        "Converted {} to the {} format in {}",
+        config.data_path.display(),
+        format,
+        path.display()
+    );
+
+    Ok(())
+}
+
+/// Print the backups of the data file, oldest first.
+fn backups(config: &Config, args: &Args) -> Result<(), Failure> {
+    args.positional(0, 0)?;
+
+    let backups = file::list_backups(&config.data_path)
+        .map_err(|e| Failure::Error(format!("could not list backups: {}", e)))?;
+    if backups.is_empty() {
+        println!("There are no backups of {}", config.data_path.display());
+    }
+    for backup in backups.iter() {
+        println!(
+            "{}",
+            backup.file_name().unwrap_or_default().to_string_lossy()
+        );
+    }
+
+    Ok(())
+}
+
+/// Replace the birds in the data file with the birds in a backup.
+/// The data file being replaced becomes a backup itself, so this can be undone.
+fn restore(config: &Config, args: &Args) -> Result<(), Failure> {
+    let backup = args.positional(1, 1)?[0];
+
+    let backup = file::find_backup(&config.data_path, backup)
+        .ok_or_else(|| Failure::Error(format!("there is no backup called {}", backup)))?;
+    let tree = file::load_tree(&backup).map_err(|e| Failure::Error(e.to_string()))?;
+
+    // keep one more backup than usual so that restoring never deletes the oldest backup
+    let format = file::file_format(&backup).unwrap_or(FileFormat::Flat);
+    file::save_tree_as(&tree, &config.data_path, config.backup_count + 1, format)
+        .map_err(|e| Failure::Error(format!("could not restore the backup: {}", e)))?;
+    println!(
+        "Restored {} to {}",
+        backup.display(),
+        config.data_path.display()
+    );
+
+    Ok(())
+}
+
+/// A change to the tree made by a command. Returns a message saying what was changed.
+type Change = fn(&mut BirdTree, &Args) -> Result<String, Failure>;
+
+/// A command that can be used in a batch script.
+struct BatchCommand {
+    name: &'static str,
+    /// The arguments the command takes
+    usage: &'static str,
+    /// Flags that take a value, e.g. `--rank genus`
+    value_flags: &'static [&'static str],
+    /// Flags that are either given or not, e.g. `--cascade`
+    switches: &'static [&'static str],
+    change: Change,
+}
+
+/// Every command that can be used in a batch script.
+const BATCH_COMMANDS: [BatchCommand; 9] = [
+    BatchCommand {
+        name: "add-group",
+        usage: "add-group <parent> <name> [--rank <rank>]",
+        value_flags: &["--rank"],
+        switches: &[],
+        change: change_add_group,
+    },
+    BatchCommand {
+        name: "add-bird",
+        usage: "add-bird <parent> <common name> <scientific name>",
+        value_flags: &[],
+        switches: &[],
+        change: change_add_bird,
+    },
+    BatchCommand {
+        name: "remove-bird",
+        usage: "remove-bird <common name>",
+        value_flags: &[],
+        switches: &[],
+        change: change_remove_bird,
+    },
+    BatchCommand {
+        name: "remove-group",
+        usage: "remove-group <group> [--cascade]",
+        value_flags: &[],
+        switches: &["--cascade"],
+        change: change_remove_group,
+    },
+    BatchCommand {
+        name: "move-bird",
+        usage: "move-bird <common name> <new parent>",
+        value_flags: &[],
+        switches: &[],
+        change: change_move_bird,
+    },
+    BatchCommand {
+        name: "move-group",
+        usage: "move-group <group> <new parent>",
+        value_flags: &[],
+        switches: &[],
+        change: change_move_group,
+    },
+    BatchCommand {
+        name: "rename-bird",
+        usage: "rename-bird <common name> <new common name>",
+        value_flags: &[],
+        switches: &[],
+        change: change_rename_bird,
+    },
+    BatchCommand {
+        name: "rename-scientific",
+        usage: "rename-scientific <common name> <new scientific name>",
+        value_flags: &[],
+        switches: &[],
+        change: change_rename_scientific,
+    },
+    BatchCommand {
+        name: "rename-group",
+        usage: "rename-group <group> <new name>",
+        value_flags: &[],
+        switches: &[],
+        change: change_rename_group,
+    },
+];
+
+/// Get the two positional arguments of a command that takes exactly two.
+fn two_args<'a>(args: &Args<'a>) -> Result<(&'a str, &'a str), Failure> {
+    match args.positional(2, 2)? {
+        [first, second] => Ok((first, second)),
+        _ => unreachable!(),
+    }
+}
+
+fn change_add_group(tree: &mut BirdTree, args: &Args) -> Result<String, Failure> {
+    let (parent, name) = two_args(args)?;
+    let rank = match args.value("--rank") {
+        Some(rank) => Some(
+            rank.parse::<Rank>()
+                .map_err(|_| Failure::Usage(format!("{} is not a taxonomic rank", rank)))?,
+        ),
+        None => None,
+    };
+
+    tree.add_group(parent, name, rank)
+        .map_err(|e| Failure::Error(add_group_error_message(e, parent, name)))?;
+    Ok(format!("Added {}, to {}", name, parent))
+}
+
+fn change_add_bird(tree: &mut BirdTree, args: &Args) -> Result<String, Failure> {
+    let (parent, name, scientific_name) = match args.positional(3, 3)? {
+        [parent, name, scientific_name] => (*parent, *name, *scientific_name),
+        _ => unreachable!(),
+    };
+
+    tree.add_bird(parent, name, scientific_name)
+        .map_err(|e| Failure::Error(add_bird_error_message(e, parent, name, scientific_name)))?;
+    Ok(format!("Added {}, to {}", name, parent))
+}
+
+fn change_remove_bird(tree: &mut BirdTree, args: &Args) -> Result<String, Failure> {
+    let name = args.positional(1, 1)?[0];
+
+    tree.remove_bird(name)
+        .map_err(|e| Failure::Error(group_error_message(e, name)))?;
+    Ok(format!("Removed {}", name))
+}
+
+fn change_remove_group(tree: &mut BirdTree, args: &Args) -> Result<String, Failure> {
+    let name = args.positional(1, 1)?[0];
+    let mode = match args.switch("--cascade") {
+        true => RemoveMode::Cascade,
+        false => RemoveMode::EmptyOnly,
+    };
+
+    tree.remove_group(name, mode)
+        .map_err(|e| Failure::Error(group_error_message(e, name)))?;
+    Ok(format!("Removed {}", name))
+}
+
+fn change_move_bird(tree: &mut BirdTree, args: &Args) -> Result<String, Failure> {
+    let (name, parent) = two_args(args)?;
+
+    tree.move_bird(name, parent)
+        .map_err(|e| Failure::Error(group_error_message(e, name)))?;
+    Ok(format!("Moved {}, to {}", name, parent))
+}
+
+fn change_move_group(tree: &mut BirdTree, args: &Args) -> Result<String, Failure> {
+    let (name, parent) = two_args(args)?;
+
+    tree.move_group(name, parent)
+        .map_err(|e| Failure::Error(group_error_message(e, name)))?;
+    Ok(format!("Moved {}, to {}", name, parent))
+}
+
+fn change_rename_bird(tree: &mut BirdTree, args: &Args) -> Result<String, Failure> {
+    let (name, new_name) = two_args(args)?;
+
+    tree.rename_bird(name, new_name)
+        .map_err(|e| Failure::Error(group_error_message(e, name)))?;
+    Ok(format!("Renamed {}, to {}", name, new_name))
+}
+
+fn change_rename_scientific(tree: &mut BirdTree, args: &Args) -> Result<String, Failure> {
+    let (name, new_name) = two_args(args)?;
+
+    tree.rename_scientific_name(name, new_name)
+        .map_err(|e| Failure::Error(group_error_message(e, name)))?;
+    Ok(format!(
+        "Renamed the scientific name of {}, to {}",
+        name, new_name
+    ))
+}
+
+fn change_rename_group(tree: &mut BirdTree, args: &Args) -> Result<String, Failure> {
+    let (name, new_name) = two_args(args)?;
+
+    tree.rename_group(name, new_name)
+        .map_err(|e| Failure::Error(group_error_message(e, name)))?;
+    Ok(format!("Renamed {}, to {}", name, new_name))
+}
+
+/// Split a line of a batch script into words the way a shell does. Words are separated by
+/// spaces and can be quoted with double or single quotes to hold spaces. Outside of single
+/// quotes, a backslash makes the next character part of the word.
+fn split_words(line: &str) -> Result<Vec<String>, String> {
+    let mut words = vec![];
+    let mut word = None::<String>;
+    let mut quote = None;
+
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match (c, quote) {
+            ('\\', None | Some('"')) => {
+                let escaped = chars.next().ok_or("the line ends with a backslash")?;
+                word.get_or_insert_default().push(escaped);
+            }
+            ('"' | '\'', None) => {
+                // an empty pair of quotes is still a word
+                word.get_or_insert_default();
+                quote = Some(c);
+            }
+            (c, Some(open)) if c == open => quote = None,
+            (c, None) if c.is_whitespace() => words.extend(word.take()),
+            (c, _) => word.get_or_insert_default().push(c),
+        }
+    }
+    if let Some(quote) = quote {
+        return Err(format!("a {} quote is never closed", quote));
+    }
+    words.extend(word);
+
+    Ok(words)
+}
+
+/// Apply one line of a batch script to the tree, returning a message saying what was changed.
+fn run_batch_line(tree: &mut BirdTree, line: &str) -> Result<String, Failure> {
+    let words = split_words(line).map_err(Failure::Usage)?;
+    let words = words.iter().map(String::as_str).collect::<Vec<_>>();
+    let Some((name, args)) = words.split_first() else {
+        return Err(Failure::Usage("the line has no command".to_string()));
+    };
+
+    let command = BATCH_COMMANDS
+        .iter()
+        .find(|command| command.name == *name)
+        .ok_or_else(|| Failure::Usage(format!("unknown command: {}", name)))?;
+
+    Args::parse(command.value_flags, command.switches, args)
+        .and_then(|args| (command.change)(tree, &args))
+        .map_err(|e| match e {
+            Failure::Usage(message) => {
+                Failure::Usage(format!("{} (usage: {})", message, command.usage))
+            }
+            e => e,
+        })
+}
+
+/// Make every change in a script, one command per line, and save the tree once at the end.
+/// Each line is reported as it is run. With `--atomic`, nothing is saved if any line fails.
+fn batch(config: &Config, args: &Args) -> Result<(), Failure> {
+    let script = args.positional(0, 1)?.first().copied().unwrap_or("-");
+    let atomic = args.switch("--atomic");
+
+    let text = match script {
+        "-" => io::read_to_string(io::stdin()),
+        path => fs::read_to_string(path),
+    }
+    .map_err(|e| Failure::Error(format!("could not read {}: {}", script, e)))?;
+
+    let mut tree = load(config)?;
+    let (mut applied, mut failed) = (0, 0);
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        match run_batch_line(&mut tree, line) {
+            Ok(message) => {
+                println!("Line {}: {}", index + 1, message);
+                applied += 1;
+            }
+            Err(Failure::Usage(message) | Failure::Error(message)) => {
+                eprintln!("Line {}: Error: {}", index + 1, message);
+                failed += 1;
+            }
+        }
+    }
+
+    if atomic && failed > 0 {
+        return Err(Failure::Error(format!(
+            "{} of {} commands failed, so no changes were saved",
+            failed,
+            applied + failed
+        )));
+    }
+    if applied > 0 {
+        save(config, &tree)?;
+    }
+    println!("Applied {} of {} commands", applied, applied + failed);
+
+    match failed {
+        0 => Ok(()),
+        failed => Err(Failure::Error(format!("{} commands failed", failed))),
+    }
+}
@@ -0,0 +1,215 @@
+use serde::Deserialize;
+
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// The name of the directory used for this program's data and config.
+const APP_DIR: &str = "zealandia-tracker";
+
+/// The environment variable that chooses the data file.
+pub const DATA_PATH_VAR: &str = "ZEALANDIA_DATA";
+
+/// The name of the data file inside the data directory.
+const DATA_FILE: &str = "birdData.json";
+
+/// How many backups of the data file are kept when nothing else is set.
+const DEFAULT_BACKUP_COUNT: usize = 5;
+
+/// Where a setting came from, from highest to lowest priority.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Source {
+    Flag,
+    Environment,
+    ConfigFile,
+    Default,
+}
+
+/// Settings read from the config file. Every setting is optional.
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct ConfigFile {
+    data_path: Option<PathBuf>,
+    backup_count: Option<usize>,
+}
+
+/// The settings the program runs with.
+pub struct Config {
+    /// The json file that birds are loaded from and saved to
+    pub data_path: PathBuf,
+
+    /// Where `data_path` was chosen
+    pub data_path_source: Source,
+
+    /// How many old versions of the data file to keep when saving
+    pub backup_count: usize,
+}
+
+/// An error that occurred while working out the settings.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// A flag was given without the value that should follow it.
+    MissingValue(String),
+    /// A flag was given a value that isn't valid for it.
+    InvalidValue(String, String),
+    /// The config file exists but could not be read.
+    Read(PathBuf, io::Error),
+    /// The config file is not valid json, or has unknown settings.
+    Parse(PathBuf, serde_json::Error),
+    /// No home directory could be found to put the data file in.
+    NoDataDir,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::MissingValue(flag) => write!(f, "{} needs a value", flag),
+            ConfigError::InvalidValue(flag, value) => {
+                write!(f, "{} is not a valid value for {}", value, flag)
+            }
+            ConfigError::Read(path, e) => {
+                write!(f, "could not read config file {}: {}", path.display(), e)
+            }
+            ConfigError::Parse(path, e) => {
+                write!(f, "config file {} is invalid: {}", path.display(), e)
+            }
+            ConfigError::NoDataDir => write!(
+                f,
+                "could not find a data directory, set {} or use --data <path>",
+                DATA_PATH_VAR
+            ),
+        }
+    }
+}
+
+/// Get a directory from an XDG environment variable, falling back to a directory in home.
+fn xdg_dir(var: &str, home_fallback: &str) -> Option<PathBuf> {
+    // the XDG spec says to ignore relative paths
+    if let Some(dir) = env::var_os(var).map(PathBuf::from)
+        && dir.is_absolute()
+    {
+        return Some(dir);
+    }
+
+    env::var_os("HOME")
+        .filter(|home| !home.is_empty())
+        .map(|home| PathBuf::from(home).join(home_fallback))
+}
+
+/// Get the path of the config file.
+pub fn config_path() -> Option<PathBuf> {
+    Some(
+        xdg_dir("XDG_CONFIG_HOME", ".config")?
+            .join(APP_DIR)
+            .join("config.json"),
+    )
+}
+
+/// Get the default path of the data file.
+fn default_data_path() -> Option<PathBuf> {
+    Some(
+        xdg_dir("XDG_DATA_HOME", ".local/share")?
+            .join(APP_DIR)
+            .join(DATA_FILE),
+    )
+}
+
+/// Get the path of the file that the history of the interactive menu is kept in.
+pub fn history_path() -> Option<PathBuf> {
+    Some(
+        xdg_dir("XDG_STATE_HOME", ".local/state")?
+            .join(APP_DIR)
+            .join("history.txt"),
+    )
+}
+
+/// Read the config file. A missing config file is the same as an empty one.
+fn read_config_file() -> Result<ConfigFile, ConfigError> {
+    let Some(path) = config_path() else {
+        return Ok(ConfigFile::default());
+    };
+
+    let json = match fs::read_to_string(&path) {
+        Ok(json) => json,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(ConfigFile::default()),
+        Err(e) => return Err(ConfigError::Read(path, e)),
+    };
+
+    serde_json::from_str(&json).map_err(|e| ConfigError::Parse(path, e))
+}
+
+/// Take the value of a flag from either `--flag value` or `--flag=value`.
+/// Returns None if `arg` is a different flag.
+fn flag_value<I>(flag: &str, arg: &str, args: &mut I) -> Option<Result<String, ConfigError>>
+where
+    I: Iterator<Item = String>,
+{
+    if arg == flag {
+        Some(
+            args.next()
+                .ok_or(ConfigError::MissingValue(flag.to_string())),
+        )
+    } else {
+        let value = arg.strip_prefix(flag)?.strip_prefix('=')?;
+        Some(Ok(value.to_string()))
+    }
+}
+
+impl Config {
+    /// Work out the settings from command line arguments, the environment and the config file.
+    /// Command line arguments should not include the program name.
+    /// Returns the settings along with the arguments that aren't settings.
+    pub fn load<I>(args: I) -> Result<(Self, Vec<String>), ConfigError>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut flag_data_path = None;
+        let mut flag_backup_count = None;
+        let mut rest = vec![];
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if let Some(value) = flag_value("--data", &arg, &mut args) {
+                flag_data_path = Some(PathBuf::from(value?));
+            } else if let Some(value) = flag_value("--backups", &arg, &mut args) {
+                let value = value?;
+                let count = value
+                    .parse::<usize>()
+                    .map_err(|_| ConfigError::InvalidValue("--backups".to_string(), value))?;
+                flag_backup_count = Some(count);
+            } else {
+                rest.push(arg);
+            }
+        }
+
+        let config_file = read_config_file()?;
+
+        let (data_path, data_path_source) = if let Some(path) = flag_data_path {
+            (path, Source::Flag)
+        } else if let Some(path) = env::var_os(DATA_PATH_VAR).filter(|path| !path.is_empty()) {
+            (PathBuf::from(path), Source::Environment)
+        } else if let Some(path) = config_file.data_path {
+            (path, Source::ConfigFile)
+        } else {
+            (
+                default_data_path().ok_or(ConfigError::NoDataDir)?,
+                Source::Default,
+            )
+        };
+
+        let backup_count = flag_backup_count
+            .or(config_file.backup_count)
+            .unwrap_or(DEFAULT_BACKUP_COUNT);
+
+        Ok((
+            Self {
+                data_path,
+                data_path_source,
+                backup_count,
+            },
+            rest,
+        ))
+    }
+}
@@ -0,0 +1,276 @@
+use crate::birds::{BirdTree, Node, NodeId};
+
+/// Escape text for HTML, or the HTML-like labels that Graphviz uses.
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Escape text for a Mermaid label, which uses entity codes that start with `#`.
+fn escape_mermaid(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '"' | '#' | '<' | '>' | '&' => format!("#{};", c as u32),
+            _ => c.to_string(),
+        })
+        .collect()
+}
+
+/// Get the HTML shown for a node, using `escape` on every piece of text from the tree.
+/// Birds show their common name above their scientific name in italics, and ranked groups show
+/// their rank below their name between the `small` tags, as DOT and Mermaid use different tags
+/// for smaller text.
+fn node_label(
+    tree: &BirdTree,
+    id: NodeId,
+    escape: fn(&str) -> String,
+    small: (&str, &str),
+) -> String {
+    match tree.get(id) {
+        Some(Node::Group {
+            name,
+            rank: Some(rank),
+        }) => format!("{}<br/>{}{}{}", escape(name), small.0, rank, small.1),
+        Some(Node::Group { name, rank: None }) => escape(name),
+        Some(Node::Bird {
+            name,
+            scientific_name,
+        }) => {
+            let scientific_name = match tree.binomial_name(id) {
+                Some(binomial_name) => binomial_name.to_string(),
+                None => scientific_name.clone(),
+            };
+            format!("{}<br/><i>{}</i>", escape(name), escape(&scientific_name))
+        }
+        None => String::new(),
+    }
+}
+
+/// Write a node and everything below it as Graphviz DOT statements, numbering each node in the
+/// order it is written.
+fn write_dot(
+    tree: &BirdTree,
+    id: NodeId,
+    parent: Option<usize>,
+    count: &mut usize,
+    out: &mut String,
+) {
+    let number = *count;
+    *count += 1;
+
+    let style = match tree.get(id) {
+        Some(Node::Bird { .. }) => "shape=ellipse, style=filled, fillcolor=\"#dff0d8\"",
+        _ => "shape=box, style=\"rounded,filled\", fillcolor=\"#e8eef7\"",
+    };
+    out.push_str(&format!(
+        "    n{} [label=<{}>, {}];\n",
+        number,
+        node_label(
+            tree,
+            id,
+            escape_html,
+            ("<font point-size=\"10\">", "</font>")
+        ),
+        style
+    ));
+    if let Some(parent) = parent {
+        out.push_str(&format!("    n{} -> n{};\n", parent, number));
+    }
+
+    for child in tree.children(id).iter() {
+        write_dot(tree, *child, Some(number), count, out);
+    }
+}
+
+/// Get a Graphviz DOT digraph of a node and everything below it. Groups are drawn as rounded
+/// boxes and birds as green ellipses.
+pub fn to_dot(tree: &BirdTree, id: NodeId) -> String {
+    let mut out = String::from("digraph taxonomy {\n");
+    out.push_str("    node [fontname=\"Helvetica\"];\n");
+    write_dot(tree, id, None, &mut 0, &mut out);
+    out.push_str("}\n");
+
+    out
+}
+
+/// Write a node and everything below it as Mermaid flowchart statements, numbering each node in
+/// the order it is written. Returns the numbers of the groups and birds that were written.
+fn write_mermaid(
+    tree: &BirdTree,
+    id: NodeId,
+    parent: Option<usize>,
+    count: &mut usize,
+    out: &mut String,
+    classes: &mut (Vec<usize>, Vec<usize>),
+) {
+    let number = *count;
+    *count += 1;
+
+    let label = node_label(tree, id, escape_mermaid, ("<small>", "</small>"));
+    match tree.get(id) {
+        Some(Node::Bird { .. }) => {
+            out.push_str(&format!("    n{}([\"{}\"])\n", number, label));
+            classes.1.push(number);
+        }
+        _ => {
+            out.push_str(&format!("    n{}[\"{}\"]\n", number, label));
+            classes.0.push(number);
+        }
+    }
+    if let Some(parent) = parent {
+        out.push_str(&format!("    n{} --> n{}\n", parent, number));
+    }
+
+    for child in tree.children(id).iter() {
+        write_mermaid(tree, *child, Some(number), count, out, classes);
+    }
+}
+
+/// Get a Mermaid flowchart of a node and everything below it. Groups are drawn as boxes and
+/// birds as green stadium shapes.
+pub fn to_mermaid(tree: &BirdTree, id: NodeId) -> String {
+    let mut out = String::from("flowchart TD\n");
+    let mut classes = (vec![], vec![]);
+    write_mermaid(tree, id, None, &mut 0, &mut out, &mut classes);
+
+    out.push_str("    classDef group fill:#e8eef7,stroke:#4a6fa5\n");
+    out.push_str("    classDef bird fill:#dff0d8,stroke:#3c763d\n");
+    for (class, numbers) in [("group", classes.0), ("bird", classes.1)] {
+        if !numbers.is_empty() {
+            let nodes = numbers
+                .iter()
+                .map(|number| format!("n{}", number))
+                .collect::<Vec<_>>();
+            out.push_str(&format!("    class {} {}\n", nodes.join(","), class));
+        }
+    }
+
+    out
+}
+
+/// Settings for drawing a tree as indented text.
+pub struct TextTreeOptions {
+    /// How many levels below the starting group to show, or None to show everything
+    pub max_depth: Option<usize>,
+    /// Whether to show how many species are inside each group
+    pub counts: bool,
+    /// Whether to draw the connecting lines with box drawing characters instead of ASCII
+    pub unicode: bool,
+}
+
+impl TextTreeOptions {
+    /// Get the connectors to draw the tree with.
+    fn connectors(&self) -> &'static Connectors {
+        match self.unicode {
+            true => &UNICODE_CONNECTORS,
+            false => &ASCII_CONNECTORS,
+        }
+    }
+}
+
+/// The pieces of text that connect the lines of a text tree.
+struct Connectors {
+    branch: &'static str,
+    last_branch: &'static str,
+    line: &'static str,
+    more: &'static str,
+}
+
+const UNICODE_CONNECTORS: Connectors = Connectors {
+    branch: "├── ",
+    last_branch: "└── ",
+    line: "│   ",
+    more: "…",
+};
+
+const ASCII_CONNECTORS: Connectors = Connectors {
+    branch: "|-- ",
+    last_branch: "`-- ",
+    line: "|   ",
+    more: "...",
+};
+
+/// Write the line for a single node of a text tree, without its connectors.
+fn text_tree_line(tree: &BirdTree, id: NodeId, options: &TextTreeOptions, hidden: bool) -> String {
+    let connectors = options.connectors();
+
+    match tree.get(id) {
+        Some(Node::Group { name, rank }) => {
+            let mut line = name.clone();
+            if let Some(rank) = rank {
+                line.push_str(&format!(" ({})", rank));
+            }
+            if options.counts {
+                line.push_str(&format!(" [{} species]", tree.count_birds(id)));
+            }
+            // show that the depth limit is hiding something
+            if hidden {
+                line.push_str(&format!(" {}", connectors.more));
+            }
+            line
+        }
+        Some(Node::Bird {
+            name,
+            scientific_name,
+        }) => {
+            let scientific_name = match tree.binomial_name(id) {
+                Some(binomial_name) => binomial_name.to_string(),
+                None => scientific_name.clone(),
+            };
+            format!("{} ({})", name, scientific_name)
+        }
+        None => String::new(),
+    }
+}
+
+/// Write the children of a node as lines of a text tree. `prefix` is the text that comes before
+/// the connector of each child, to continue the lines of the groups above.
+fn write_text_tree(
+    tree: &BirdTree,
+    id: NodeId,
+    options: &TextTreeOptions,
+    depth: usize,
+    prefix: &str,
+    out: &mut String,
+) {
+    let connectors = options.connectors();
+
+    let children = tree.children(id);
+    for (i, child) in children.iter().enumerate() {
+        let is_last = i + 1 == children.len();
+        let hidden = options.max_depth.is_some_and(|max| depth + 1 >= max)
+            && !tree.children(*child).is_empty();
+
+        out.push_str(prefix);
+        out.push_str(match is_last {
+            true => connectors.last_branch,
+            false => connectors.branch,
+        });
+        out.push_str(&text_tree_line(tree, *child, options, hidden));
+        out.push('\n');
+
+        if !hidden {
+            let prefix = match is_last {
+                true => format!("{}    ", prefix),
+                false => format!("{}{}", prefix, connectors.line),
+            };
+            write_text_tree(tree, *child, options, depth + 1, &prefix, out);
+        }
+    }
+}
+
+/// Draw a node and everything below it as an indented tree of text, with lines connecting each
+/// group to the groups and birds inside it.
+pub fn to_text_tree(tree: &BirdTree, id: NodeId, options: &TextTreeOptions) -> String {
+    let hidden = options.max_depth == Some(0) && !tree.children(id).is_empty();
+
+    let mut out = text_tree_line(tree, id, options, hidden);
+    out.push('\n');
+    if !hidden {
+        write_text_tree(tree, id, options, 0, "", &mut out);
+    }
+
+    out
+}
@@ -1,9 +1,13 @@
-use crate::birds::{BirdTree, Node};
+use crate::birds::{BirdTree, GroupError, Node, NodeId, Rank, RejectedData, ScientificName};
 use serde::{Deserialize, Serialize};
 
+use std::collections::HashSet;
+use std::fmt;
 use std::fs::{self, File};
-use std::io::Write;
-use std::rc::Rc;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -13,46 +17,1781 @@ pub struct BirdData {
     pub common_name: String,
 }
 
-/// Load data from json and deserialize it into BirdData.
-pub fn load_to_tree(tree: &mut BirdTree) {
-    let json = fs::read_to_string("birdData.json").expect("Could not read from file");
-    let birds =
-        serde_json::from_str::<Vec<BirdData>>(&json).expect("Json is formatted incorrectly");
+/// A group stored in a data file. Groups are stored before the groups inside them.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupData {
+    /// The names of the groups above this one, starting at the root. Empty for the root itself.
+    pub parent_nodes: Vec<String>,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rank: Option<Rank>,
+}
 
-    for bird in birds.iter() {
-        tree.insert_data(bird);
+/// A group or bird in a nested data file. Groups hold the groups and birds inside them, so the
+/// names of the groups above a node never need to be repeated.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum NestedNode {
+    Group {
+        name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        rank: Option<Rank>,
+        children: Vec<NestedNode>,
+    },
+    Bird {
+        #[serde(rename = "commonName")]
+        common_name: String,
+        name: String,
+    },
+}
+
+/// The layouts a data file can be written in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileFormat {
+    /// Separate lists of groups and birds, each with the names of every group above it.
+    Flat,
+    /// A single root group, with `children` lists holding the groups and birds inside each group.
+    Nested,
+}
+
+impl FileFormat {
+    /// Every file format, in the order they should be shown to the user.
+    pub const ALL: [FileFormat; 2] = [FileFormat::Flat, FileFormat::Nested];
+
+    /// Get the name of the file format.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FileFormat::Flat => "flat",
+            FileFormat::Nested => "nested",
+        }
+    }
+
+    /// Work out which format a data file is in from its json.
+    fn detect(value: &serde_json::Value) -> Self {
+        if value.get("tree").is_some() {
+            FileFormat::Nested
+        } else {
+            FileFormat::Flat
+        }
+    }
+}
+
+impl fmt::Display for FileFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseFormatError;
+
+impl FromStr for FileFormat {
+    type Err = ParseFormatError;
+
+    /// Parse a file format from its name, ignoring case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        FileFormat::ALL
+            .into_iter()
+            .find(|format| format.as_str().eq_ignore_ascii_case(s))
+            .ok_or(ParseFormatError)
+    }
+}
+
+/// The version of the data file format that this program writes.
+/// Bump this and add a migration to `MIGRATIONS` whenever the format changes.
+pub const SCHEMA_VERSION: u64 = 3;
+
+/// Upgrades a data file by one version. The function at index `i` upgrades a file from version
+/// `i + 1` to version `i + 2`.
+type Migration = fn(serde_json::Value) -> Result<serde_json::Value, String>;
+
+const MIGRATIONS: [Migration; SCHEMA_VERSION as usize - 1] = [migrate_v1_to_v2, migrate_v2_to_v3];
+
+/// Version 1 files are a bare list of the birds added on top of the built in birds, which were
+/// always loaded first. Version 2 wraps the list with a schema version and a metadata block, and
+/// holds every bird, so the built in birds are added to the start of the list. Files that were
+/// saved with the built in birds already in them keep their own copies instead.
+fn migrate_v1_to_v2(value: serde_json::Value) -> Result<serde_json::Value, String> {
+    let serde_json::Value::Array(birds) = value else {
+        return Err("a version 1 file must be a list".to_string());
+    };
+    let seed_birds =
+        serde_json::from_str::<Vec<serde_json::Value>>(SEED_DATA).expect("Seed data is valid");
+
+    let common_name = |bird: &serde_json::Value| {
+        bird.get("commonName")
+            .and_then(serde_json::Value::as_str)
+            .map(|name| name.trim().to_lowercase())
+    };
+    let file_names = birds.iter().filter_map(common_name).collect::<HashSet<_>>();
+    let birds = seed_birds
+        .into_iter()
+        .filter(|bird| common_name(bird).is_none_or(|name| !file_names.contains(&name)))
+        .chain(birds)
+        .collect::<Vec<_>>();
+
+    Ok(serde_json::json!({
+        "schemaVersion": 2,
+        "metadata": {},
+        "birds": birds,
+    }))
+}
+
+/// Version 3 files also store every group, so that groups without birds are kept. Older files
+/// only have the groups that can be found from the birds' parent nodes.
+fn migrate_v2_to_v3(mut value: serde_json::Value) -> Result<serde_json::Value, String> {
+    let object = value
+        .as_object_mut()
+        .ok_or("a version 2 file must be an object")?;
+    object.insert("schemaVersion".to_string(), 3.into());
+    object.insert("groups".to_string(), serde_json::json!([]));
+
+    Ok(value)
+}
+
+/// Information about a data file that isn't part of the tree.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Metadata {
+    /// When the file was last saved, in RFC 3339 format
+    pub saved_at: Option<String>,
+    /// The program and version that last saved the file
+    pub saved_by: Option<String>,
+}
+
+/// The contents of a data file in the current schema version.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DataFile {
+    schema_version: u64,
+    #[serde(default)]
+    metadata: Metadata,
+    groups: Vec<GroupData>,
+    birds: Vec<BirdData>,
+}
+
+/// The contents of a nested data file. Nested files were added in schema version 3.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NestedDataFile {
+    schema_version: u64,
+    #[serde(default)]
+    metadata: Metadata,
+    tree: NestedNode,
+}
+
+/// The first schema version that nested data files can have.
+const FIRST_NESTED_VERSION: u64 = 3;
+
+/// An error that occurred while saving the tree.
+#[derive(Debug)]
+pub enum SaveError {
+    /// The tree could not be converted to json.
+    Serialize(serde_json::Error),
+    /// The json could not be written to disk.
+    Io(io::Error),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::Serialize(e) => write!(f, "could not convert birds to json: {}", e),
+            SaveError::Io(e) => write!(f, "could not write to the data file: {}", e),
+        }
+    }
+}
+
+impl From<serde_json::Error> for SaveError {
+    fn from(e: serde_json::Error) -> Self {
+        SaveError::Serialize(e)
+    }
+}
+
+impl From<io::Error> for SaveError {
+    fn from(e: io::Error) -> Self {
+        SaveError::Io(e)
+    }
+}
+
+/// An error that occurred while loading the tree.
+#[derive(Debug)]
+pub enum LoadError {
+    /// There is no data file at the path.
+    NotFound(PathBuf),
+    /// The data file exists but could not be read.
+    Read(PathBuf, io::Error),
+    /// The data file was written by a newer version of this program.
+    UnsupportedVersion(PathBuf, u64),
+    /// The data file could not be upgraded from an older version.
+    Migration {
+        path: PathBuf,
+        from_version: u64,
+        message: String,
+    },
+    /// The data file is not in the expected json format.
+    /// The line and column are 0 when the problem was found after upgrading an older file.
+    Parse {
+        path: PathBuf,
+        /// The line the problem was found on, starting at 1
+        line: usize,
+        /// The column the problem was found at, starting at 1
+        column: usize,
+        error: serde_json::Error,
+    },
+    /// The data file has groups or birds that can't be added to the tree, such as ones that
+    /// reuse a name.
+    Rejected {
+        path: PathBuf,
+        records: Vec<DroppedRecord>,
+    },
+}
+
+impl LoadError {
+    /// Build a parse error from a `serde_json` error.
+    fn parse(path: &Path, error: serde_json::Error) -> Self {
+        LoadError::Parse {
+            path: path.to_path_buf(),
+            line: error.line(),
+            column: error.column(),
+            error,
+        }
+    }
+}
+
+/// A group or bird in a data file that was left out while loading because it could not be read.
+#[derive(Debug)]
+pub struct DroppedRecord {
+    /// Either "group" or "bird"
+    pub kind: &'static str,
+    /// The position of the record in its list in the file, starting at 0
+    pub index: usize,
+    /// Why the record could not be read
+    pub reason: String,
+}
+
+impl From<RejectedData> for DroppedRecord {
+    fn from(rejected: RejectedData) -> Self {
+        DroppedRecord {
+            kind: rejected.kind,
+            index: rejected.index,
+            reason: rejected_reason(
+                rejected.kind,
+                &rejected.name,
+                rejected.clash.as_deref(),
+                rejected.error,
+            ),
+        }
+    }
+}
+
+/// Get the reason that a group or bird from a file could not be added to the tree. `clash` is
+/// the group in its path whose name is already used elsewhere in the tree, if there is one.
+fn rejected_reason(kind: &str, name: &str, clash: Option<&str>, error: GroupError) -> String {
+    match (error, clash) {
+        (GroupError::DuplicateNameError, Some(group)) => {
+            format!(
+                "the name of the group {} is already used by another group",
+                group
+            )
+        }
+        (GroupError::DuplicateNameError, None) => {
+            format!("{} uses a name that is already in the tree", name)
+        }
+        (_, _) => format!("the {} {} could not be added to the tree", kind, name),
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::NotFound(path) => write!(f, "there is no data file at {}", path.display()),
+            LoadError::Read(path, e) => write!(f, "could not read {}: {}", path.display(), e),
+            LoadError::UnsupportedVersion(path, version) => write!(
+                f,
+                "{} uses version {} of the file format, but only versions up to {} are supported",
+                path.display(),
+                version,
+                SCHEMA_VERSION
+            ),
+            LoadError::Migration {
+                path,
+                from_version,
+                message,
+            } => write!(
+                f,
+                "could not upgrade {} from version {}: {}",
+                path.display(),
+                from_version,
+                message
+            ),
+            LoadError::Parse { path, error, .. } => {
+                write!(f, "{} is formatted incorrectly: {}", path.display(), error)
+            }
+            LoadError::Rejected { path, records } => {
+                write!(
+                    f,
+                    "{} has {} groups or birds that can't be added to the tree",
+                    path.display(),
+                    records.len()
+                )?;
+                if let Some(record) = records.first() {
+                    write!(
+                        f,
+                        ", starting with {} {}: {}",
+                        record.kind,
+                        record.index + 1,
+                        record.reason
+                    )?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The built in taxonomy, used when there is no saved data yet.
+const SEED_DATA: &str = include_str!("../seedData.json");
+
+/// Build a tree from the built in taxonomy.
+pub fn seed_tree() -> BirdTree {
+    let (groups, birds) =
+        parse_data_file(Path::new("seedData.json"), SEED_DATA).expect("Seed data is valid");
+
+    // the seed data has no repeated names, so nothing is left out
+    BirdTree::from_data(&groups, &birds)
+        .expect("Seed data contains birds")
+        .0
+}
+
+/// Get the schema version of a data file. Files without a version are from before versions
+/// were added, which makes them version 1.
+fn schema_version(path: &Path, value: &serde_json::Value) -> Result<u64, LoadError> {
+    match value {
+        serde_json::Value::Array(_) => Ok(1),
+        serde_json::Value::Object(object) => match object.get("schemaVersion") {
+            Some(version) => version.as_u64().ok_or_else(|| LoadError::Migration {
+                path: path.to_path_buf(),
+                from_version: 0,
+                message: format!("{} is not a valid schema version", version),
+            }),
+            None => Err(LoadError::Migration {
+                path: path.to_path_buf(),
+                from_version: 0,
+                message: "the file has no schemaVersion".to_string(),
+            }),
+        },
+        _ => Err(LoadError::Migration {
+            path: path.to_path_buf(),
+            from_version: 0,
+            message: "the file is not a list or an object".to_string(),
+        }),
+    }
+}
+
+/// Parse the json of a data file into a value in the current schema version, upgrading it from
+/// an older version if needed. Returns the value and the version it was upgraded from.
+fn migrate(path: &Path, json: &str) -> Result<(serde_json::Value, u64), LoadError> {
+    let mut value =
+        serde_json::from_str::<serde_json::Value>(json).map_err(|e| LoadError::parse(path, e))?;
+
+    let version = schema_version(path, &value)?;
+    if version == 0 || version > SCHEMA_VERSION {
+        return Err(LoadError::UnsupportedVersion(path.to_path_buf(), version));
+    }
+
+    for (from_version, migration) in (version..).zip(&MIGRATIONS[version as usize - 1..]) {
+        value = migration(value).map_err(|message| LoadError::Migration {
+            path: path.to_path_buf(),
+            from_version,
+            message,
+        })?;
+    }
+
+    Ok((value, version))
+}
+
+/// Check that a nested data file has a schema version that nested files can have. Nested files
+/// have not changed since they were added, so they never need upgrading.
+fn check_nested_version(path: &Path, value: &serde_json::Value) -> Result<(), LoadError> {
+    let version = schema_version(path, value)?;
+    if version > SCHEMA_VERSION {
+        return Err(LoadError::UnsupportedVersion(path.to_path_buf(), version));
+    }
+    if version < FIRST_NESTED_VERSION {
+        return Err(LoadError::Migration {
+            path: path.to_path_buf(),
+            from_version: version,
+            message: format!(
+                "nested files were added in version {}",
+                FIRST_NESTED_VERSION
+            ),
+        });
+    }
+
+    Ok(())
+}
+
+/// Parse the json of a data file in any schema version or format, returning its groups and birds.
+fn parse_data_file(path: &Path, json: &str) -> Result<(Vec<GroupData>, Vec<BirdData>), LoadError> {
+    let value =
+        serde_json::from_str::<serde_json::Value>(json).map_err(|e| LoadError::parse(path, e))?;
+
+    match FileFormat::detect(&value) {
+        FileFormat::Flat => {
+            let (value, version) = migrate(path, json)?;
+
+            let data = if version == SCHEMA_VERSION {
+                // parse the original text so that errors point at the right line
+                serde_json::from_str::<DataFile>(json)
+            } else {
+                serde_json::from_value::<DataFile>(value)
+            }
+            .map_err(|e| LoadError::parse(path, e))?;
+
+            Ok((data.groups, data.birds))
+        }
+        FileFormat::Nested => {
+            check_nested_version(path, &value)?;
+            let data = serde_json::from_str::<NestedDataFile>(json)
+                .map_err(|e| LoadError::parse(path, e))?;
+
+            let (mut groups, mut birds) = (vec![], vec![]);
+            flatten_nested(data.tree, &mut vec![], &mut groups, &mut birds);
+
+            Ok((groups, birds))
+        }
+    }
+}
+
+/// Add a nested node and everything inside it to lists of groups and birds.
+/// `parents` holds the names of the groups above the node.
+fn flatten_nested(
+    node: NestedNode,
+    parents: &mut Vec<String>,
+    groups: &mut Vec<GroupData>,
+    birds: &mut Vec<BirdData>,
+) {
+    match node {
+        NestedNode::Group {
+            name,
+            rank,
+            children,
+        } => {
+            groups.push(GroupData {
+                parent_nodes: parents.clone(),
+                name: name.clone(),
+                rank,
+            });
+
+            parents.push(name);
+            for child in children {
+                flatten_nested(child, parents, groups, birds);
+            }
+            parents.pop();
+        }
+        NestedNode::Bird { common_name, name } => birds.push(BirdData {
+            parent_nodes: parents.clone(),
+            name,
+            common_name,
+        }),
+    }
+}
+
+/// Get a path with a suffix added to the end of the file name.
+fn path_with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+
+    PathBuf::from(path)
+}
+
+/// Get the directory that backups of a data file are kept in.
+fn backup_dir(path: &Path) -> PathBuf {
+    path.parent().unwrap_or(Path::new("")).join("backups")
+}
+
+/// Get the start of the file name of every backup of a data file.
+fn backup_prefix(path: &Path) -> String {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    format!("{}-", stem)
+}
+
+/// Get the timestamp and counter of a backup of a data file from its file name, or None if the
+/// file isn't a backup of that data file. Backups are named `<stem>-<timestamp>.json`, with a
+/// `_<counter>` after the timestamp when there was already a backup in the same second.
+fn backup_stamp(path: &Path, name: &str) -> Option<(String, usize)> {
+    let digits = |text: &str| !text.is_empty() && text.bytes().all(|b| b.is_ascii_digit());
+
+    let stamp = name
+        .strip_prefix(&backup_prefix(path))?
+        .strip_suffix(".json")?;
+    let (stamp, count) = match stamp.split_once('_') {
+        Some((stamp, count)) if digits(count) => (stamp, count.parse().ok()?),
+        Some(_) => return None,
+        None => (stamp, 0),
+    };
+    // e.g. 20251016-093000, so that backups of birdData-2.json don't look like birdData.json's
+    let (date, time) = stamp.split_once('-')?;
+    (date.len() == 8 && digits(date) && time.len() == 6 && digits(time))
+        .then(|| (stamp.to_string(), count))
+}
+
+/// Get the current time in UTC as (year, month, day, hour, minute, second).
+fn utc_now() -> (u64, u64, u64, u64, u64, u64) {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or(0);
+    let (days, secs_of_day) = (secs / 86400, secs % 86400);
+
+    // convert days since 1970-01-01 to a civil date
+    // (http://howardhinnant.github.io/date_algorithms.html#civil_from_days)
+    let z = days + 719468;
+    let era = z / 146097;
+    let day_of_era = z - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+    (
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day / 60 % 60,
+        secs_of_day % 60,
+    )
+}
+
+/// Get the current time as a timestamp that sorts in time order, e.g. 20251016-093000.
+fn timestamp() -> String {
+    let (year, month, day, hour, minute, second) = utc_now();
+    format!(
+        "{:04}{:02}{:02}-{:02}{:02}{:02}",
+        year, month, day, hour, minute, second
+    )
+}
+
+/// Get the current time in RFC 3339 format, e.g. 2025-10-16T09:30:00Z.
+fn rfc3339_now() -> String {
+    let (year, month, day, hour, minute, second) = utc_now();
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year, month, day, hour, minute, second
+    )
+}
+
+/// Get the paths of every backup of a data file, from oldest to newest.
+pub fn list_backups(path: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut backups = vec![];
+
+    let entries = match fs::read_dir(backup_dir(path)) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e),
+    };
+    for entry in entries {
+        let backup = entry?.path();
+        let name = backup.file_name().unwrap_or_default().to_string_lossy();
+        if let Some(stamp) = backup_stamp(path, &name) {
+            backups.push((stamp, backup));
+        }
+    }
+
+    // timestamps sort in time order, and so do the counters of backups in the same second
+    backups.sort();
+
+    Ok(backups.into_iter().map(|(_, backup)| backup).collect())
+}
+
+/// Find a backup of a data file from its file name, or its path.
+pub fn find_backup(path: &Path, backup: &str) -> Option<PathBuf> {
+    list_backups(path).ok()?.into_iter().find(|other| {
+        other.file_name().is_some_and(|name| name == backup) || other == Path::new(backup)
+    })
+}
+
+/// Copy the data file into a new timestamped backup, then delete the oldest backups so that only
+/// `keep` are left.
+fn rotate_backups(path: &Path, keep: usize) -> io::Result<()> {
+    let dir = backup_dir(path);
+    fs::create_dir_all(&dir)?;
+
+    // add a counter in the unlikely case of two saves in the same second, carrying on from the
+    // highest one so that the new backup still sorts last after older ones have been deleted
+    let stamp = timestamp();
+    let count = list_backups(path)?
+        .iter()
+        .filter_map(|backup| {
+            let name = backup.file_name()?.to_string_lossy();
+            backup_stamp(path, &name)
+        })
+        .filter(|(other, _)| *other == stamp)
+        .map(|(_, count)| count + 1)
+        .max();
+    let backup = match count {
+        Some(count) => dir.join(format!("{}{}_{}.json", backup_prefix(path), stamp, count)),
+        None => dir.join(format!("{}{}.json", backup_prefix(path), stamp)),
+    };
+    fs::copy(path, &backup)?;
+
+    let backups = list_backups(path)?;
+    for old in backups.iter().take(backups.len().saturating_sub(keep)) {
+        fs::remove_file(old)?;
+    }
+
+    Ok(())
+}
+
+/// Read a data file to a string.
+fn read_data_file(path: &Path) -> Result<String, LoadError> {
+    match fs::read_to_string(path) {
+        Ok(json) => Ok(json),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            Err(LoadError::NotFound(path.to_path_buf()))
+        }
+        Err(e) => Err(LoadError::Read(path.to_path_buf(), e)),
+    }
+}
+
+/// Load the tree from a json file.
+pub fn load_tree(path: &Path) -> Result<BirdTree, LoadError> {
+    let json = read_data_file(path)?;
+    let (groups, birds) = parse_data_file(path, &json)?;
+
+    // a file without any birds has nothing to build a tree from
+    let Some((tree, rejected)) = BirdTree::from_data(&groups, &birds) else {
+        return Ok(seed_tree());
+    };
+    if !rejected.is_empty() {
+        return Err(LoadError::Rejected {
+            path: path.to_path_buf(),
+            records: rejected.into_iter().map(DroppedRecord::from).collect(),
+        });
+    }
+
+    Ok(tree)
+}
+
+/// Read the records in one of the lists of a data file, leaving out any that can't be read or
+/// have no parent nodes when `needs_parent` is set.
+fn read_records<T>(
+    list: serde_json::Value,
+    kind: &'static str,
+    parent_nodes: fn(&T) -> &[String],
+    needs_parent: bool,
+    dropped: &mut Vec<DroppedRecord>,
+) -> Result<Vec<T>, serde_json::Error>
+where
+    T: for<'de> Deserialize<'de>,
+{
+    let mut records = vec![];
+    for (index, record) in serde_json::from_value::<Vec<serde_json::Value>>(list)?
+        .into_iter()
+        .enumerate()
+    {
+        match serde_json::from_value::<T>(record) {
+            Ok(record) if needs_parent && parent_nodes(&record).is_empty() => {
+                dropped.push(DroppedRecord {
+                    kind,
+                    index,
+                    reason: format!("the {} has no parent nodes", kind),
+                })
+            }
+            Ok(record) => records.push(record),
+            Err(e) => dropped.push(DroppedRecord {
+                kind,
+                index,
+                reason: e.to_string(),
+            }),
+        }
+    }
+
+    Ok(records)
+}
+
+/// Read a node of a nested data file and everything inside it, leaving out any groups or birds
+/// that can't be read. A group that can't be read is left out along with everything inside it.
+fn read_nested_records(
+    mut node: serde_json::Value,
+    parents: &mut Vec<String>,
+    groups: &mut Vec<GroupData>,
+    birds: &mut Vec<BirdData>,
+    dropped: &mut Vec<DroppedRecord>,
+) {
+    // read the group on its own first, so that a broken child doesn't take its siblings with it
+    let children = match node.get_mut("children") {
+        Some(serde_json::Value::Array(children)) => Some(std::mem::take(children)),
+        _ => None,
+    };
+    let (kind, index) = match children {
+        Some(_) => ("group", groups.len()),
+        None => ("bird", birds.len()),
+    };
+    let index = index + dropped.iter().filter(|record| record.kind == kind).count();
+
+    match serde_json::from_value::<NestedNode>(node) {
+        Ok(NestedNode::Group { name, rank, .. }) if let Some(children) = children => {
+            groups.push(GroupData {
+                parent_nodes: parents.clone(),
+                name: name.clone(),
+                rank,
+            });
+
+            parents.push(name);
+            for child in children {
+                read_nested_records(child, parents, groups, birds, dropped);
+            }
+            parents.pop();
+        }
+        Ok(NestedNode::Bird { common_name, name }) if children.is_none() && !parents.is_empty() => {
+            birds.push(BirdData {
+                parent_nodes: parents.clone(),
+                name,
+                common_name,
+            })
+        }
+        Ok(NestedNode::Bird { .. }) if children.is_none() => dropped.push(DroppedRecord {
+            kind,
+            index,
+            reason: "the bird is not inside a group".to_string(),
+        }),
+        _ => dropped.push(DroppedRecord {
+            kind,
+            index,
+            reason: format!("the {} is missing a name or has an invalid field", kind),
+        }),
+    }
+}
+
+/// Load the tree from a json file, leaving out any groups or birds that can't be read.
+/// A flat file must still contain json lists of groups and birds. Returns what was left out.
+pub fn load_tree_skipping_bad_records(
+    path: &Path,
+) -> Result<(BirdTree, Vec<DroppedRecord>), LoadError> {
+    let json = read_data_file(path)?;
+    let value =
+        serde_json::from_str::<serde_json::Value>(&json).map_err(|e| LoadError::parse(path, e))?;
+
+    let mut dropped = vec![];
+    let (groups, birds) = match FileFormat::detect(&value) {
+        FileFormat::Flat => {
+            let (mut value, _) = migrate(path, &json)?;
+
+            let groups = read_records::<GroupData>(
+                value["groups"].take(),
+                "group",
+                |group| &group.parent_nodes,
+                false,
+                &mut dropped,
+            )
+            .map_err(|e| LoadError::parse(path, e))?;
+            let birds = read_records::<BirdData>(
+                value["birds"].take(),
+                "bird",
+                |bird| &bird.parent_nodes,
+                true,
+                &mut dropped,
+            )
+            .map_err(|e| LoadError::parse(path, e))?;
+
+            (groups, birds)
+        }
+        FileFormat::Nested => {
+            check_nested_version(path, &value)?;
+
+            let (mut groups, mut birds) = (vec![], vec![]);
+            read_nested_records(
+                value["tree"].clone(),
+                &mut vec![],
+                &mut groups,
+                &mut birds,
+                &mut dropped,
+            );
+
+            (groups, birds)
+        }
+    };
+
+    let tree = match BirdTree::from_data(&groups, &birds) {
+        Some((tree, rejected)) => {
+            dropped.extend(rejected.into_iter().map(DroppedRecord::from));
+            tree
+        }
+        None => seed_tree(),
+    };
+
+    Ok((tree, dropped))
+}
+
+/// Move a data file that couldn't be loaded out of the way, so that saving doesn't overwrite
+/// anything that could be fixed by hand, or replace the backup with it. Returns the new path.
+pub fn set_aside(path: &Path) -> io::Result<PathBuf> {
+    let new_path = path_with_suffix(path, ".corrupt");
+    fs::rename(path, &new_path)?;
+
+    Ok(new_path)
+}
+
+/// Get the names of every group above a node, starting at the root.
+fn parent_names(tree: &BirdTree, id: NodeId) -> Option<Vec<String>> {
+    let mut parent_nodes = vec![];
+    for group in tree.lineage(id) {
+        parent_nodes.push(tree.get(group)?.name().to_string());
     }
+
+    Some(parent_nodes)
+}
+
+/// Get a group data structure from a group so that it can be saved to json.
+fn group_data_from_group(tree: &BirdTree, group: NodeId) -> Option<GroupData> {
+    let parent_nodes = parent_names(tree, group)?;
+
+    let group = tree.get(group)?;
+    Some(GroupData {
+        parent_nodes,
+        name: group.name().to_string(),
+        rank: group.rank(),
+    })
 }
 
 /// Get a bird data structure from a bird so that it can be saved to json.
-fn bird_data_from_bird(bird: Rc<Node>) -> BirdData {
-    let parent_nodes = bird
-        .full_scientific_name()
-        .unwrap()
-        .split(" ")
-        .map(|s| s.to_lowercase())
-        .collect::<Vec<String>>();
-
-    BirdData {
+fn bird_data_from_bird(tree: &BirdTree, bird: NodeId) -> Option<BirdData> {
+    let parent_nodes = parent_names(tree, bird)?;
+
+    let bird = tree.get(bird)?;
+    Some(BirdData {
         parent_nodes,
         common_name: bird.name().to_string(),
         name: bird.scientific_name().to_string(),
+    })
+}
+
+/// Get a nested node for a node and everything inside it so that it can be saved to json.
+fn nested_node(tree: &BirdTree, id: NodeId) -> Option<NestedNode> {
+    match tree.get(id)? {
+        Node::Group { name, rank } => Some(NestedNode::Group {
+            name: name.clone(),
+            rank: *rank,
+            children: tree
+                .children(id)
+                .iter()
+                .filter_map(|child| nested_node(tree, *child))
+                .collect(),
+        }),
+        Node::Bird {
+            name,
+            scientific_name,
+        } => Some(NestedNode::Bird {
+            common_name: name.clone(),
+            name: scientific_name.clone(),
+        }),
     }
 }
 
-/// Save an entire tree to json.
-pub fn save_tree(tree: &BirdTree) {
-    let mut data = vec![];
+/// Get the format of an existing data file, or None if it can't be read.
+pub fn file_format(path: &Path) -> Option<FileFormat> {
+    let json = fs::read_to_string(path).ok()?;
+    let value = serde_json::from_str::<serde_json::Value>(&json).ok()?;
+
+    Some(FileFormat::detect(&value))
+}
+
+/// Write bytes to a path by writing to a temporary file first and renaming it into place,
+/// so that a failed write never leaves a half written file behind. `before_replace` runs once
+/// the temporary file has been written, just before it replaces the file at `path`.
+fn write_atomic(
+    path: &Path,
+    contents: &[u8],
+    before_replace: impl FnOnce() -> io::Result<()>,
+) -> io::Result<()> {
+    let tmp_path = path_with_suffix(path, ".tmp");
+
+    let result = File::create(&tmp_path).and_then(|mut file| {
+        file.write_all(contents)?;
+        file.sync_all()
+    });
+
+    match result
+        .and_then(|()| before_replace())
+        .and_then(|()| fs::rename(&tmp_path, path))
+    {
+        Ok(()) => Ok(()),
+        Err(e) => {
+            // don't leave the temporary file lying around
+            let _ = fs::remove_file(&tmp_path);
+            Err(e)
+        }
+    }
+}
+
+/// Save an entire tree to a json file, creating its directory if needed.
+/// The file keeps the format it is already in, or is saved flat if it doesn't exist yet.
+/// The file being replaced is kept as a backup, along with up to `keep_backups` older ones.
+pub fn save_tree(tree: &BirdTree, path: &Path, keep_backups: usize) -> Result<(), SaveError> {
+    let format = file_format(path).unwrap_or(FileFormat::Flat);
+
+    save_tree_as(tree, path, keep_backups, format)
+}
+
+/// Save an entire tree to a json file in a particular format, creating its directory if needed.
+/// The file being replaced is kept as a backup, along with up to `keep_backups` older ones.
+pub fn save_tree_as(
+    tree: &BirdTree,
+    path: &Path,
+    keep_backups: usize,
+    format: FileFormat,
+) -> Result<(), SaveError> {
+    let metadata = Metadata {
+        saved_at: Some(rfc3339_now()),
+        saved_by: Some(format!(
+            "{} {}",
+            env!("CARGO_PKG_NAME"),
+            env!("CARGO_PKG_VERSION")
+        )),
+    };
+
+    let json = match format {
+        FileFormat::Flat => {
+            let mut groups = vec![];
+            let mut birds = vec![];
+
+            // groups are saved from the top down so that they are loaded before the groups
+            // inside them
+            for node in tree.preorder(tree.root()) {
+                match tree.get(node) {
+                    Some(Node::Group { .. }) => groups.extend(group_data_from_group(tree, node)),
+                    Some(Node::Bird { .. }) => birds.extend(bird_data_from_bird(tree, node)),
+                    None => {}
+                }
+            }
+
+            serde_json::to_string(&DataFile {
+                schema_version: SCHEMA_VERSION,
+                metadata,
+                groups,
+                birds,
+            })?
+        }
+        // nested files are meant to be edited by hand, so they are indented
+        FileFormat::Nested => serde_json::to_string_pretty(&NestedDataFile {
+            schema_version: SCHEMA_VERSION,
+            metadata,
+            tree: nested_node(tree, tree.root()).expect("The root is in the tree"),
+        })?,
+    };
+
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    // keep the previous save around in case this one turns out to be wrong, but only once the
+    // new save has been written, so that failed saves don't push the oldest backups out
+    write_atomic(path, json.as_bytes(), || {
+        if keep_backups > 0 && path.exists() {
+            rotate_backups(path, keep_backups)?;
+        }
+        Ok(())
+    })?;
+
+    Ok(())
+}
+
+/// A column of a species CSV file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CsvField {
+    /// The name of the group at a rank. The species column holds the species epithet.
+    Rank(Rank),
+    ScientificName,
+    CommonName,
+}
+
+impl CsvField {
+    /// Get the header that the column is exported with.
+    pub fn header(&self) -> &'static str {
+        match self {
+            CsvField::Rank(rank) => rank.as_str(),
+            CsvField::ScientificName => "scientificName",
+            CsvField::CommonName => "commonName",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseCsvFieldError;
+
+impl FromStr for CsvField {
+    type Err = ParseCsvFieldError;
+
+    /// Parse a column from a CSV header, ignoring case, spaces, dashes and underscores so that
+    /// "Scientific Name", "scientific_name" and "scientificName" are all understood.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let header = s
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != '-' && *c != '_')
+            .collect::<String>()
+            .to_lowercase();
 
-    // get bird data for each bird and push it to data accumulator
-    for node in tree.direct_parents.iter() {
-        for child in node.children().unwrap().borrow().iter() {
-            data.push(bird_data_from_bird(Rc::clone(&child)));
+        match header.as_str() {
+            "scientificname" | "scientific" | "latinname" | "binomial" => {
+                Ok(CsvField::ScientificName)
+            }
+            "commonname" | "common" | "vernacularname" => Ok(CsvField::CommonName),
+            _ => header
+                .parse::<Rank>()
+                .map(CsvField::Rank)
+                .map_err(|_| ParseCsvFieldError),
         }
     }
+}
+
+/// An error that stopped a CSV file from being imported at all.
+#[derive(Debug)]
+pub enum CsvError {
+    /// The CSV file could not be read.
+    Read(PathBuf, io::Error),
+    /// A quoted field is never closed. The line is where the field starts.
+    UnclosedQuote(PathBuf, usize),
+    /// The CSV file has no header row.
+    Empty(PathBuf),
+    /// The header has no column for a field that every bird needs.
+    MissingColumn(PathBuf, &'static str),
+    /// A header given in a mapping is not in the CSV file.
+    UnknownHeader(PathBuf, String),
+}
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CsvError::Read(path, e) => write!(f, "could not read {}: {}", path.display(), e),
+            CsvError::UnclosedQuote(path, line) => write!(
+                f,
+                "{} has a quoted field starting on line {} that is never closed",
+                path.display(),
+                line
+            ),
+            CsvError::Empty(path) => write!(f, "{} has no header row", path.display()),
+            CsvError::MissingColumn(path, column) => {
+                write!(f, "{} has no {} column", path.display(), column)
+            }
+            CsvError::UnknownHeader(path, header) => {
+                write!(f, "{} has no column called {}", path.display(), header)
+            }
+        }
+    }
+}
+
+/// A row of a CSV file that could not be placed in the tree.
+pub struct RejectedRow {
+    /// The line the row starts on, starting at 1 for the header
+    pub line: usize,
+    /// Why the row could not be placed
+    pub reason: String,
+}
+
+/// What happened when a CSV file was imported.
+pub struct CsvImport {
+    /// How many birds were added to the tree
+    pub imported: usize,
+    /// The rows that could not be placed in the tree
+    pub rejected: Vec<RejectedRow>,
+    /// The headers of columns that were not recognised and were ignored
+    pub ignored_columns: Vec<String>,
+}
+
+/// Split CSV text into rows of fields, following RFC 4180. Fields can be quoted to hold commas,
+/// line breaks and quotes, which are written twice inside quotes.
+/// Returns each row with the line it starts on, or the line of a quoted field that isn't closed.
+fn parse_csv(text: &str) -> Result<Vec<(usize, Vec<String>)>, usize> {
+    // spreadsheet programs often start the file with a byte order mark
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+
+    let mut rows = vec![];
+    let mut row = vec![];
+    let mut field = String::new();
+    let (mut line, mut row_line, mut quote_line) = (1, 1, 1);
+    let mut in_quotes = false;
+
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, in_quotes) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            ('"', true) => in_quotes = false,
+            ('"', false) if field.is_empty() => {
+                in_quotes = true;
+                quote_line = line;
+            }
+            (',', false) => row.push(std::mem::take(&mut field)),
+            ('\r', false) if chars.peek() == Some(&'\n') => {}
+            ('\n' | '\r', false) => {
+                row.push(std::mem::take(&mut field));
+                rows.push((row_line, std::mem::take(&mut row)));
+                line += 1;
+                row_line = line;
+            }
+            _ => {
+                if c == '\n' {
+                    line += 1;
+                }
+                field.push(c);
+            }
+        }
+    }
+    if in_quotes {
+        return Err(quote_line);
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push((row_line, row));
+    }
+
+    // blank lines hold nothing to import
+    rows.retain(|(_, row)| row.iter().any(|field| !field.trim().is_empty()));
+
+    Ok(rows)
+}
+
+/// Quote a CSV field if it holds anything that would otherwise be read as part of the layout.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) || field.trim() != field {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Work out where a bird from a CSV row belongs. The row's groups don't need to start at the
+/// root, as long as one of them is already in the tree, in which case the groups above the
+/// lowest one that is are taken from the tree. Returns the path of group names and the rank of each group.
+fn csv_path(
+    tree: &BirdTree,
+    groups: &[(Rank, &str)],
+) -> Result<(Vec<String>, Vec<Option<Rank>>), String> {
+    let (start, known) = groups
+        .iter()
+        .enumerate()
+        .rev()
+        .find_map(|(i, (_, name))| Some((i, tree.get_group_with_name(name)?)))
+        .ok_or_else(|| {
+            let names = groups.iter().map(|(_, name)| *name).collect::<Vec<_>>();
+            match names.is_empty() {
+                true => "the row has no groups".to_string(),
+                false => format!("none of {} are in the tree", names.join(", ")),
+            }
+        })?;
+
+    // the groups above it in the row must be above it in the tree as well
+    let lineage = tree.lineage(known);
+    if let Some((_, name)) = groups[..start].iter().find(|(_, name)| {
+        tree.get_group_with_name(name)
+            .is_some_and(|group| !lineage.contains(&group))
+    }) {
+        return Err(format!(
+            "{} is not above {} in the tree",
+            name, groups[start].1
+        ));
+    }
+
+    let mut path = vec![];
+    let mut ranks = vec![];
+    for group in lineage {
+        path.push(
+            tree.get(group)
+                .map(Node::name)
+                .unwrap_or_default()
+                .to_string(),
+        );
+        ranks.push(None);
+    }
+    for (rank, name) in groups[start..].iter() {
+        path.push(name.to_string());
+        ranks.push(Some(*rank));
+    }
+
+    Ok((path, ranks))
+}
+
+/// Import the species in a CSV file into a tree. The first row is a header naming each column,
+/// which is matched to a rank, the scientific name or the common name. `mapping` can name
+/// columns whose headers aren't recognised, and overrides the recognised ones.
+/// Rows that can't be placed in the tree are left out and reported in the result.
+pub fn import_csv(
+    tree: &mut BirdTree,
+    path: &Path,
+    mapping: &[(String, CsvField)],
+) -> Result<CsvImport, CsvError> {
+    let text = fs::read_to_string(path).map_err(|e| CsvError::Read(path.to_path_buf(), e))?;
+    let mut rows = parse_csv(&text)
+        .map_err(|line| CsvError::UnclosedQuote(path.to_path_buf(), line))?
+        .into_iter();
+    let (_, header) = rows.next().ok_or(CsvError::Empty(path.to_path_buf()))?;
+
+    let mut columns = header
+        .iter()
+        .map(|name| name.parse::<CsvField>().ok())
+        .collect::<Vec<_>>();
+    for (name, field) in mapping.iter() {
+        let column = header
+            .iter()
+            .position(|other| other.trim().eq_ignore_ascii_case(name.trim()))
+            .ok_or_else(|| CsvError::UnknownHeader(path.to_path_buf(), name.clone()))?;
+        columns[column] = Some(*field);
+    }
+
+    let ignored_columns = header
+        .iter()
+        .zip(columns.iter())
+        .filter(|(_, field)| field.is_none())
+        .map(|(name, _)| name.clone())
+        .collect();
+    if !columns.contains(&Some(CsvField::CommonName)) {
+        return Err(CsvError::MissingColumn(path.to_path_buf(), "common name"));
+    }
+    let has_species = columns.contains(&Some(CsvField::Rank(Rank::Genus)))
+        && columns.contains(&Some(CsvField::Rank(Rank::Species)));
+    if !columns.contains(&Some(CsvField::ScientificName)) && !has_species {
+        return Err(CsvError::MissingColumn(
+            path.to_path_buf(),
+            "scientific name",
+        ));
+    }
+
+    // the scientific names of every bird, to find rows that are already in the tree
+    let mut known = tree
+        .preorder(tree.root())
+        .into_iter()
+        .filter_map(|node| tree.binomial_name(node))
+        .map(|name| name.to_string().to_lowercase())
+        .collect::<HashSet<_>>();
+
+    let mut import = CsvImport {
+        imported: 0,
+        rejected: vec![],
+        ignored_columns,
+    };
+    for (line, row) in rows {
+        let reject = |reason: String| RejectedRow { line, reason };
+
+        if row.len() != header.len() {
+            import.rejected.push(reject(format!(
+                "the row has {} fields, but the header has {}",
+                row.len(),
+                header.len()
+            )));
+            continue;
+        }
+
+        // the last column for a field wins if there are several
+        let cell = |wanted: CsvField| {
+            columns
+                .iter()
+                .zip(row.iter())
+                .filter(|(field, _)| **field == Some(wanted))
+                .map(|(_, cell)| cell.trim())
+                .next_back()
+                .filter(|cell| !cell.is_empty())
+        };
+
+        let Some(common_name) = cell(CsvField::CommonName) else {
+            import
+                .rejected
+                .push(reject("the row has no common name".to_string()));
+            continue;
+        };
+        // birds only store their species epithet, and get their genus from the groups above
+        let (genus, species) = match (
+            cell(CsvField::ScientificName),
+            cell(CsvField::Rank(Rank::Genus)),
+            cell(CsvField::Rank(Rank::Species)),
+        ) {
+            (_, genus, Some(species)) => (genus, species),
+            (Some(name), genus, None) => match name.split_once(char::is_whitespace) {
+                Some((name_genus, _)) if genus.is_some_and(|genus| genus != name_genus) => {
+                    import.rejected.push(reject(format!(
+                        "{} is not in the genus {}",
+                        name,
+                        genus.unwrap_or_default()
+                    )));
+                    continue;
+                }
+                Some((name_genus, species)) => (Some(name_genus), species.trim()),
+                None => (genus, name),
+            },
+            _ => {
+                import
+                    .rejected
+                    .push(reject("the row has no scientific name".to_string()));
+                continue;
+            }
+        };
 
-    let mut file = File::open("birdData.json").unwrap();
-    let json = serde_json::to_string(&data).unwrap();
-    file.write_all(json.as_bytes())
-        .expect("Failed to write to file");
+        // species epithets are part of the scientific name, not groups of their own
+        let mut groups = Rank::ALL
+            .into_iter()
+            .filter(|rank| !matches!(rank, Rank::Genus | Rank::Species))
+            .filter_map(|rank| Some((rank, cell(CsvField::Rank(rank))?)))
+            .chain(genus.map(|genus| (Rank::Genus, genus)))
+            .collect::<Vec<_>>();
+        groups.sort_by_key(|(rank, _)| *rank);
+
+        // the same limit as adding birds and groups from the menu
+        if common_name.len() > 50
+            || species.len() > 50
+            || groups.iter().any(|(_, name)| name.len() > 50)
+        {
+            import
+                .rejected
+                .push(reject("names must be 50 characters or less".to_string()));
+            continue;
+        }
+        let (parent_nodes, ranks) = match csv_path(tree, &groups) {
+            Ok(path) => path,
+            Err(reason) => {
+                import.rejected.push(reject(reason));
+                continue;
+            }
+        };
+
+        // birds without a genus are named after the group they are in
+        let binomial = ScientificName::new(
+            genus
+                .or(parent_nodes.last().map(String::as_str))
+                .unwrap_or_default(),
+            species,
+            None,
+        )
+        .to_string();
+        if tree.search_by_name(common_name).is_some() {
+            import
+                .rejected
+                .push(reject(format!("{} is already in the tree", common_name)));
+            continue;
+        }
+        if !known.insert(binomial.to_lowercase()) {
+            import
+                .rejected
+                .push(reject(format!("{} is already in the tree", binomial)));
+            continue;
+        }
+
+        let clash = tree.clashing_group(&parent_nodes).map(str::to_string);
+        if let Err(e) = tree.insert_data_with_ranks(
+            &BirdData {
+                parent_nodes,
+                name: species.to_string(),
+                common_name: common_name.to_string(),
+            },
+            &ranks,
+        ) {
+            import.rejected.push(reject(rejected_reason(
+                "bird",
+                common_name,
+                clash.as_deref(),
+                e,
+            )));
+            continue;
+        }
+        import.imported += 1;
+    }
+
+    Ok(import)
+}
+
+/// Write every bird in a tree as CSV, with a column for each rank used above the birds, then the
+/// scientific and common names. Unranked groups have no column, so they are left out.
+pub fn to_csv(tree: &BirdTree) -> String {
+    let birds = tree
+        .preorder(tree.root())
+        .into_iter()
+        .filter(|node| tree.get(*node).is_some_and(|node| !node.is_group()))
+        .collect::<Vec<_>>();
+
+    let mut ranks = birds
+        .iter()
+        .flat_map(|bird| tree.lineage(*bird))
+        .filter_map(|group| tree.get(group)?.rank())
+        .collect::<Vec<_>>();
+    ranks.sort();
+    ranks.dedup();
+
+    let columns = ranks
+        .into_iter()
+        .map(CsvField::Rank)
+        .chain([CsvField::ScientificName, CsvField::CommonName])
+        .collect::<Vec<_>>();
+
+    let mut csv = String::new();
+    let header = columns.iter().map(|field| csv_field(field.header()));
+    csv.push_str(&header.collect::<Vec<_>>().join(","));
+    csv.push_str("\r\n");
+
+    for bird in birds.iter() {
+        let row = columns.iter().map(|field| {
+            let cell = match field {
+                CsvField::Rank(rank) => tree
+                    .ancestor_with_rank(*bird, *rank)
+                    .and_then(|group| tree.get(group))
+                    .map(|group| group.name().to_string()),
+                CsvField::ScientificName => tree.binomial_name(*bird).map(|name| name.to_string()),
+                CsvField::CommonName => tree.get(*bird).map(|bird| bird.name().to_string()),
+            };
+            csv_field(&cell.unwrap_or_default())
+        });
+        csv.push_str(&row.collect::<Vec<_>>().join(","));
+        csv.push_str("\r\n");
+    }
+
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{TestDir, bird_names};
+
+    fn rows(text: &str) -> Vec<(usize, Vec<String>)> {
+        parse_csv(text).unwrap()
+    }
+
+    fn row(line: usize, fields: &[&str]) -> (usize, Vec<String>) {
+        (line, fields.iter().map(|field| field.to_string()).collect())
+    }
+
+    #[test]
+    fn parse_csv_splits_rows_and_fields() {
+        assert_eq!(
+            rows("genus,commonName\r\nNestor,Kea\nApteryx,Little Spotted Kiwi"),
+            vec![
+                row(1, &["genus", "commonName"]),
+                row(2, &["Nestor", "Kea"]),
+                row(3, &["Apteryx", "Little Spotted Kiwi"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_csv_reads_quoted_fields() {
+        assert_eq!(
+            rows("\"Nestor, the parrots\",\"the \"\"mountain\"\" parrot\",\"\"\n"),
+            vec![row(
+                1,
+                &["Nestor, the parrots", "the \"mountain\" parrot", ""]
+            )]
+        );
+    }
+
+    #[test]
+    fn parse_csv_keeps_line_breaks_in_quotes() {
+        // rows report the line they start on, counting the lines inside quotes
+        assert_eq!(
+            rows("commonName,notes\nKea,\"seen in\nArthur's Pass\"\nKaka,\n"),
+            vec![
+                row(1, &["commonName", "notes"]),
+                row(2, &["Kea", "seen in\nArthur's Pass"]),
+                row(4, &["Kaka", ""]),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_csv_skips_byte_order_mark_and_blank_lines() {
+        assert_eq!(
+            rows("\u{feff}commonName\n\n , \nKea\n"),
+            vec![row(1, &["commonName"]), row(4, &["Kea"])]
+        );
+    }
+
+    #[test]
+    fn parse_csv_reports_unclosed_quote() {
+        assert_eq!(
+            parse_csv("commonName,notes\nKea,ok\nKaka,\"never\nclosed\n"),
+            Err(3)
+        );
+    }
+
+    #[test]
+    fn csv_fields_round_trip() {
+        let fields = ["Kea", "a, b", "say \"hi\"", "two\nlines", " padded ", ""];
+        let text = fields.map(csv_field).join(",");
+
+        assert_eq!(rows(&text), vec![row(1, &fields)]);
+    }
+
+    #[test]
+    fn csv_headers_are_recognised() {
+        for header in [
+            "Scientific Name",
+            "scientific_name",
+            "scientificName",
+            "binomial",
+        ] {
+            assert_eq!(
+                header.parse::<CsvField>().ok(),
+                Some(CsvField::ScientificName)
+            );
+        }
+        for header in ["Common name", "vernacular-name"] {
+            assert_eq!(header.parse::<CsvField>().ok(), Some(CsvField::CommonName));
+        }
+        assert_eq!(
+            "Family".parse::<CsvField>().ok(),
+            Some(CsvField::Rank(Rank::Family))
+        );
+        assert!("notes".parse::<CsvField>().is_err());
+    }
+
+    #[test]
+    fn import_csv_uses_header_mapping() {
+        let dir = TestDir::new("import_csv_uses_header_mapping");
+        let path = dir.file(
+            "mapping.csv",
+            "Family,Genus,Species name,Common name,Notes\n\
+             Strigopidae,Nestor,Nestor example,Example parrot,made up\n\
+             Strigopidae,Nestor,Nestor notabilis,Kea,already in the tree\n\
+             Nowhere,Nullus,Nullus nemo,Nobody,not in the tree\n",
+        );
+        let mut tree = seed_tree();
+        let mapping = [("species NAME".to_string(), CsvField::ScientificName)];
+
+        let import = import_csv(&mut tree, &path, &mapping).unwrap();
+
+        assert_eq!(import.imported, 1);
+        assert_eq!(import.ignored_columns, vec!["Notes".to_string()]);
+        assert_eq!(
+            import
+                .rejected
+                .iter()
+                .map(|row| row.line)
+                .collect::<Vec<_>>(),
+            vec![3, 4]
+        );
+        let bird = tree.search_by_scientific_name("Nestor example").unwrap();
+        assert_eq!(tree.get(bird).unwrap().name(), "Example parrot");
+    }
+
+    #[test]
+    fn import_csv_starts_from_the_lowest_known_group() {
+        let dir = TestDir::new("import_csv_starts_from_the_lowest_known_group");
+        let path = dir.file(
+            "lowest.csv",
+            "class,family,genus,scientificName,commonName\n\
+             Aves,,Nestor,Nestor x,Other\n\
+             Aves,Apterygidae,Nestor,Nestor y,Misplaced\n\
+             Aves,Dup,Dup,Dup z,Clash\n",
+        );
+        let mut tree = seed_tree();
+
+        let import = import_csv(&mut tree, &path, &[]).unwrap();
+
+        assert_eq!(import.imported, 1);
+        // the bird goes in the Nestor that is already in the tree, not a new one below Aves
+        let bird = tree.search_by_name("Other").unwrap();
+        assert_eq!(tree.parent(bird), tree.get_group_with_name("Nestor"));
+        let reasons = import
+            .rejected
+            .iter()
+            .map(|row| row.reason.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            reasons,
+            vec![
+                "Apterygidae is not above Nestor in the tree",
+                "the name of the group Dup is already used by another group",
+            ]
+        );
+    }
+
+    #[test]
+    fn import_csv_limits_the_length_of_group_names() {
+        let dir = TestDir::new("import_csv_limits_the_length_of_group_names");
+        let family = "F".repeat(80);
+        let path = dir.file(
+            "long.csv",
+            &format!(
+                "order,family,genus,species,commonName\nPsittaciformes,{family},Longus,x,Long\n"
+            ),
+        );
+        let mut tree = seed_tree();
+
+        let import = import_csv(&mut tree, &path, &[]).unwrap();
+
+        assert_eq!(import.imported, 0);
+        assert_eq!(
+            import.rejected[0].reason,
+            "names must be 50 characters or less"
+        );
+        assert!(tree.get_group_with_name(&family).is_none());
+    }
+
+    #[test]
+    fn rejected_records_name_the_group_that_clashes() {
+        let bird = |path: &[&str], name: &str, common_name: &str| BirdData {
+            parent_nodes: path.iter().map(|group| group.to_string()).collect(),
+            name: name.to_string(),
+            common_name: common_name.to_string(),
+        };
+        let birds = [
+            bird(&["Aves", "Psittaciformes", "Nestor"], "notabilis", "Kea"),
+            bird(&["Aves", "Passeriformes", "Nestor"], "x", "Other"),
+            bird(&["Aves", "Psittaciformes", "Nestor"], "y", "Kea"),
+        ];
+
+        let (_, rejected) = BirdTree::from_data(&[], &birds).unwrap();
+        let reasons = rejected
+            .into_iter()
+            .map(|rejected| DroppedRecord::from(rejected).reason)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            reasons,
+            vec![
+                "the name of the group Nestor is already used by another group",
+                "Kea uses a name that is already in the tree",
+            ]
+        );
+    }
+
+    #[test]
+    fn import_csv_needs_common_and_scientific_names() {
+        let dir = TestDir::new("import_csv_needs_common_and_scientific_names");
+        let path = dir.file("missing.csv", "genus,commonName\nNestor,Kea\n");
+        let mut tree = seed_tree();
+
+        assert!(matches!(
+            import_csv(&mut tree, &path, &[]),
+            Err(CsvError::MissingColumn(_, "scientific name"))
+        ));
+        assert!(matches!(
+            import_csv(&mut tree, &path, &[("latin".to_string(), CsvField::ScientificName)]),
+            Err(CsvError::UnknownHeader(_, header)) if header == "latin"
+        ));
+    }
+
+    #[test]
+    fn import_csv_reports_unclosed_quote() {
+        let dir = TestDir::new("import_csv_reports_unclosed_quote");
+        let path = dir.file("unclosed.csv", "scientificName,commonName\n\"Nestor,Kea\n");
+        let mut tree = seed_tree();
+
+        assert!(matches!(
+            import_csv(&mut tree, &path, &[]),
+            Err(CsvError::UnclosedQuote(_, 2))
+        ));
+    }
+
+    #[test]
+    fn csv_export_round_trips() {
+        let dir = TestDir::new("csv_export_round_trips");
+        let path = dir.file("export.csv", &to_csv(&seed_tree()));
+        let mut tree = BirdTree::new(Node::new_group("Animalia", Some(Rank::Kingdom))).unwrap();
+
+        let import = import_csv(&mut tree, &path, &[]).unwrap();
+
+        assert!(import.rejected.is_empty());
+        assert_eq!(bird_names(&tree), bird_names(&seed_tree()));
+    }
+
+    #[test]
+    fn v1_files_are_added_to_the_seed_birds() {
+        let json = r#"[
+            {"parentNodes": ["Animalia", "Chordata", "Aves", "Gruiformes", "Rallidae", "Porphyrio"],
+             "name": "hochstetteri", "commonName": "Takahē"},
+            {"parentNodes": ["Animalia", "Chordata", "Aves", "Psittaciformes", "Strigopidae", "Nestor"],
+             "name": "notabilis", "commonName": " KEA "}
+        ]"#;
+        let path = Path::new("v1.json");
+
+        let (value, version) = migrate(path, json).unwrap();
+        assert_eq!(version, 1);
+        assert_eq!(value["schemaVersion"], SCHEMA_VERSION);
+        assert_eq!(value["groups"], serde_json::json!([]));
+
+        let (groups, birds) = parse_data_file(path, json).unwrap();
+        let (tree, rejected) = BirdTree::from_data(&groups, &birds).unwrap();
+        assert!(rejected.is_empty());
+
+        // the file's own copy of a seed bird replaces it
+        let names = bird_names(&tree);
+        assert_eq!(names.len(), 6);
+        assert!(names.contains(&(" KEA ".to_string(), "Nestor notabilis".to_string())));
+        assert!(names.contains(&("Takahē".to_string(), "Porphyrio hochstetteri".to_string())));
+        assert!(names.contains(&("Kaka".to_string(), "Nestor meridionalis".to_string())));
+    }
+
+    #[test]
+    fn v2_files_gain_groups() {
+        let json = r#"{"schemaVersion": 2, "metadata": {"savedBy": "test"}, "birds": []}"#;
+
+        let (value, version) = migrate(Path::new("v2.json"), json).unwrap();
+
+        assert_eq!(version, 2);
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "schemaVersion": 3,
+                "metadata": {"savedBy": "test"},
+                "groups": [],
+                "birds": [],
+            })
+        );
+    }
+
+    #[test]
+    fn unknown_versions_are_not_migrated() {
+        let path = Path::new("future.json");
+
+        assert!(matches!(
+            migrate(path, r#"{"schemaVersion": 4, "birds": []}"#),
+            Err(LoadError::UnsupportedVersion(_, 4))
+        ));
+        assert!(matches!(
+            migrate(path, r#"{"birds": []}"#),
+            Err(LoadError::Migration { .. })
+        ));
+    }
+
+    #[test]
+    fn failed_saves_keep_the_backups() {
+        let dir = TestDir::new("failed_saves_keep_the_backups");
+        let path = dir.path("birdData.json");
+        let tree = seed_tree();
+        for _ in 0..3 {
+            save_tree(&tree, &path, 2).unwrap();
+        }
+        let backups = list_backups(&path).unwrap();
+        assert_eq!(backups.len(), 2);
+
+        // a directory in the way of the temporary file makes every save fail
+        fs::create_dir(path_with_suffix(&path, ".tmp")).unwrap();
+        for _ in 0..3 {
+            assert!(save_tree(&tree, &path, 2).is_err());
+        }
+
+        assert_eq!(list_backups(&path).unwrap(), backups);
+    }
+
+    #[test]
+    fn backups_only_match_their_own_data_file() {
+        let path = Path::new("birdData.json");
+
+        assert_eq!(
+            backup_stamp(path, "birdData-20251016-093000.json"),
+            Some(("20251016-093000".to_string(), 0))
+        );
+        assert_eq!(
+            backup_stamp(path, "birdData-20251016-093000_12.json"),
+            Some(("20251016-093000".to_string(), 12))
+        );
+        assert_eq!(backup_stamp(path, "birdData-2-20251016-093000.json"), None);
+        assert_eq!(backup_stamp(path, "birdData-20251016-093000_.json"), None);
+        assert_eq!(backup_stamp(path, "birdData-notes.json"), None);
+    }
 }
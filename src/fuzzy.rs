@@ -0,0 +1,153 @@
+use std::collections::HashSet;
+
+/// Lowercase a name and take the accents off its letters, so that "Tūī" and "tui" are the same.
+fn fold(name: &str) -> String {
+    name.trim()
+        .to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'ā' | 'á' | 'à' | 'â' | 'ä' | 'ã' | 'å' => 'a',
+            'ē' | 'é' | 'è' | 'ê' | 'ë' => 'e',
+            'ī' | 'í' | 'ì' | 'î' | 'ï' => 'i',
+            'ō' | 'ó' | 'ò' | 'ô' | 'ö' | 'õ' | 'ø' => 'o',
+            'ū' | 'ú' | 'ù' | 'û' | 'ü' => 'u',
+            'ý' | 'ÿ' => 'y',
+            'ñ' => 'n',
+            'ç' => 'c',
+            c => c,
+        })
+        .collect()
+}
+
+/// Count the characters that have to be inserted, removed or swapped to turn `a` into `b`.
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    // only the previous row of the table is needed to work out the next one
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    let mut current = vec![0; b.len() + 1];
+
+    for (i, a_char) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, b_char) in b.iter().enumerate() {
+            let swap = previous[j] + usize::from(a_char != b_char);
+            current[j + 1] = swap.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[b.len()]
+}
+
+/// Get every run of three characters in a name, padded so that the start and end of the
+/// name count for more.
+fn trigrams(name: &[char]) -> HashSet<[char; 3]> {
+    let padded = [' ', ' ']
+        .iter()
+        .chain(name)
+        .chain([' '].iter())
+        .copied()
+        .collect::<Vec<_>>();
+
+    padded
+        .windows(3)
+        .map(|window| [window[0], window[1], window[2]])
+        .collect()
+}
+
+/// Score how alike two names are from 0 to 1, where 1 means they are the same once folded.
+/// This is the better of the edit distance compared to the length of the longer name, which
+/// suits typos, and the share of trigrams in common, which suits words in a different order.
+fn similarity(a: &str, b: &str) -> f64 {
+    let a = fold(a).chars().collect::<Vec<_>>();
+    let b = fold(b).chars().collect::<Vec<_>>();
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+
+    let edit_score = 1.0 - edit_distance(&a, &b) as f64 / longest as f64;
+
+    let (a_trigrams, b_trigrams) = (trigrams(&a), trigrams(&b));
+    let shared = a_trigrams.intersection(&b_trigrams).count();
+    let trigram_score = shared as f64 / (a_trigrams.len() + b_trigrams.len() - shared) as f64;
+
+    edit_score.max(trigram_score)
+}
+
+/// Score how well a search matches a name from 0 to 1. A search of a single word is also
+/// compared with each word of the name, so that "bellbrid" still finds "New Zealand bellbird".
+pub fn match_score(query: &str, name: &str) -> f64 {
+    let whole_name = similarity(query, name);
+    if query.split_whitespace().count() != 1 {
+        return whole_name;
+    }
+
+    name.split_whitespace()
+        .map(|word| similarity(query, word))
+        .fold(whole_name, f64::max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The score that `BirdTree::fuzzy_search` needs before it suggests a name.
+    const CLOSE: f64 = 0.6;
+
+    #[test]
+    fn edit_distance_counts_changes() {
+        let distance = |a: &str, b: &str| {
+            edit_distance(
+                &a.chars().collect::<Vec<_>>(),
+                &b.chars().collect::<Vec<_>>(),
+            )
+        };
+
+        assert_eq!(distance("kea", "kea"), 0);
+        assert_eq!(distance("", "kea"), 3);
+        assert_eq!(distance("kaka", "kea"), 2);
+        assert_eq!(distance("bellbird", "bellbrid"), 2);
+    }
+
+    #[test]
+    fn same_names_score_one() {
+        assert_eq!(match_score("Kea", "Kea"), 1.0);
+        assert_eq!(match_score("  tui ", "Tūī"), 1.0);
+        assert_eq!(match_score("kaka", "Kākā"), 1.0);
+        assert_eq!(match_score("", ""), 1.0);
+    }
+
+    #[test]
+    fn typos_are_close() {
+        assert!(match_score("Piwakwaka", "Piwakawaka") >= CLOSE);
+        assert!(match_score("little spoted kiwi", "Little Spotted Kiwi") >= CLOSE);
+        // a single word is also matched against each word of the name
+        assert!(match_score("bellbrid", "New Zealand bellbird") >= CLOSE);
+        assert!(similarity("bellbrid", "New Zealand bellbird") < CLOSE);
+    }
+
+    #[test]
+    fn words_in_another_order_are_close() {
+        assert!(match_score("kiwi little spotted", "Little Spotted Kiwi") >= CLOSE);
+    }
+
+    #[test]
+    fn different_names_are_not_close() {
+        assert!(match_score("Takahē", "Kea") < CLOSE);
+        assert!(match_score("hihi", "New Zealand bellbird") < CLOSE);
+        assert!(match_score("kiwi", "") < CLOSE);
+    }
+
+    #[test]
+    fn scores_stay_between_zero_and_one() {
+        for (query, name) in [("a", "zzzzzzzz"), ("x y z", "Kea"), ("Tūī", "tui tui")] {
+            let score = match_score(query, name);
+            assert!(
+                (0.0..=1.0).contains(&score),
+                "{} for {} and {}",
+                score,
+                query,
+                name
+            );
+        }
+    }
+}
@@ -1,10 +1,29 @@
 mod birds;
+mod commands;
+mod config;
+mod diagram;
 mod file;
+mod fuzzy;
+mod newick;
+mod output;
+mod prompt;
+#[cfg(test)]
+mod testing;
+mod ui;
 
+use std::env;
 use std::io::{Write, stdin, stdout};
+use std::process::ExitCode;
 use std::str::FromStr;
 
-use birds::GroupError;
+use birds::{BirdTree, GroupError, Rank, RemoveMode};
+use config::Config;
+use diagram::TextTreeOptions;
+use prompt::{Completion, Interrupt, Prompt};
+use ui::{
+    add_bird_error_message, add_group_error_message, group_error_message, print_bird,
+    suggest_birds, supports_unicode,
+};
 
 /// Returns the user input parsed to the type T
 fn get_user_input<T>() -> Option<T>
@@ -19,13 +38,16 @@ where
     buf.trim().parse::<T>().ok()
 }
 
-fn main() {
-    // build the tree
-    let mut tree = birds::build_tree();
-
-    // load json contents into the tree
-    file::load_to_tree(&mut tree);
+/// Print the reason that a change to a group or bird could not be made.
+/// `name` is the name of the group or bird that the change was made to.
+fn print_group_error(e: GroupError, name: &str) {
+    println!("{}", group_error_message(e, name));
+}
 
+/// Run the interactive menu until the user exits and the tree is saved.
+/// Ctrl-C cancels the current task, and Ctrl-D saves and exits. Returns a failure if the tree
+/// couldn't be saved after the input ended, as there is no way to ask the user to try again.
+fn run_menu(config: &Config, mut tree: BirdTree, prompt: &mut Prompt) -> ExitCode {
     let message = "Welcome to Zealandia Tracker.\n
             Please choose a task:
             1. Search for bird by common name
@@ -33,18 +55,36 @@ fn main() {
             3. See all birds in a specific group
             4. Add new classification
             5. Add new species
-            6. Exit\n
-            Enter a choice (1-6):";
+            6. Remove a species
+            7. Remove a classification
+            8. Move a species or classification
+            9. Rename a species or classification
+            10. Show a classification as a tree
+            11. Exit\n
+            Enter a choice (1-11):";
 
     // Program loop
+    let mut exit_pressed = false;
     loop {
-        println!("{}", message);
-        // get choice from user until a valid integer is entered
-        let choice = loop {
-            if let Some(choice) = get_user_input::<u32>() {
-                break choice;
+        let choice = if exit_pressed {
+            11
+        } else {
+            prompt.set_names(&tree);
+            println!("{}", message);
+            // get choice from user until a valid integer is entered
+            loop {
+                if let Some(choice) = prompt.read::<u32>(Completion::Nothing) {
+                    break choice;
+                }
+                match prompt.take_interrupt() {
+                    Some(Interrupt::Exit) => {
+                        exit_pressed = true;
+                        break 11;
+                    }
+                    Some(Interrupt::Cancel) => {}
+                    None => println!("Please enter a number"),
+                }
             }
-            println!("Please enter a number");
         };
 
         // perform actions on the user's choice
@@ -52,33 +92,39 @@ fn main() {
             1 => {
                 // search for bird details by name
                 println!("Enter the name of the bird:");
-                if let Some(name) = get_user_input::<String>() {
+                if let Some(name) = prompt.read::<String>(Completion::Birds) {
                     if let Some(bird) = tree.search_by_name(&name) {
-                        println!("\n{}\n", bird);
+                        print_bird(&tree, bird);
                     } else {
                         println!("Could not find bird: {}", &name);
+                        if let Some(suggestion) = suggest_birds(&tree, &name) {
+                            println!("{}", suggestion);
+                        }
                     }
                 }
             }
             2 => {
                 // search for bird details by scientific name
                 println!("Enter the scientific name of the bird:");
-                if let Some(name) = get_user_input::<String>() {
+                if let Some(name) = prompt.read::<String>(Completion::ScientificNames) {
                     if let Some(bird) = tree.search_by_scientific_name(&name) {
-                        println!("\n{}\n", bird);
+                        print_bird(&tree, bird);
                     } else {
                         println!("Could not find bird with scientific name: {}", &name);
+                        if let Some(suggestion) = suggest_birds(&tree, &name) {
+                            println!("{}", suggestion);
+                        }
                     }
                 }
             }
             3 => {
                 // get all birds in a group
                 println!("Enter the bird group:");
-                if let Some(group_name) = get_user_input::<String>() {
+                if let Some(group_name) = prompt.read::<String>(Completion::Groups) {
                     match tree.birds_in_group_from_name(&group_name) {
                         Ok(birds) => {
                             for bird in birds.iter() {
-                                println!("{}\n", bird);
+                                print_bird(&tree, *bird);
                             }
                         }
                         Err(_) => {
@@ -90,21 +136,28 @@ fn main() {
             4 => {
                 // add a group
                 println!("Enter the parent group");
-                if let Some(parent_group) = get_user_input::<String>() {
+                if let Some(parent_group) = prompt.read::<String>(Completion::Groups) {
                     println!("Enter the new group name");
-                    if let Some(new_group) = get_user_input::<String>() {
-                        match tree.add_group(&parent_group, &new_group) {
-                            Ok(()) => {
-                                println!("Added {}, to {}\n", &new_group, &parent_group);
+                    if let Some(new_group) = prompt.read::<String>(Completion::Nothing) {
+                        println!("Enter the new group's rank (e.g. order, family, genus)");
+                        println!("Leave this blank for an unranked group");
+                        if let Some(rank) = prompt.read::<String>(Completion::Nothing) {
+                            let rank = match rank.as_str() {
+                                "" => Ok(None),
+                                rank => rank.parse::<Rank>().map(Some),
+                            };
+                            match rank {
+                                Ok(rank) => match tree.add_group(&parent_group, &new_group, rank) {
+                                    Ok(()) => {
+                                        println!("Added {}, to {}\n", &new_group, &parent_group);
+                                    }
+                                    Err(e) => println!(
+                                        "{}",
+                                        add_group_error_message(e, &parent_group, &new_group)
+                                    ),
+                                },
+                                Err(_) => println!("That is not a taxonomic rank"),
                             }
-                            Err(e) => match e {
-                                GroupError::NoGroupExistsErr => {
-                                    println!("There is no group with name: {}", &parent_group);
-                                }
-                                GroupError::InputOutsideOfBoundsError => {
-                                    println!("Please enter a valid string with length 1-50");
-                                }
-                            },
                         }
                     }
                 }
@@ -112,34 +165,203 @@ fn main() {
             5 => {
                 // add a bird
                 println!("Enter the parent group");
-                if let Some(parent_group) = get_user_input::<String>() {
+                if let Some(parent_group) = prompt.read::<String>(Completion::Groups) {
                     println!("Enter the new bird name");
-                    if let Some(name) = get_user_input::<String>() {
+                    if let Some(name) = prompt.read::<String>(Completion::Nothing) {
                         println!("Enter the new bird's scientific name");
-                        if let Some(scientific_name) = get_user_input::<String>() {
+                        if let Some(scientific_name) = prompt.read::<String>(Completion::Nothing) {
                             match tree.add_bird(&parent_group, &name, &scientific_name) {
                                 Ok(()) => {
                                     println!("Added {}, to {}\n", &name, &parent_group);
                                 }
-                                Err(e) => match e {
-                                    GroupError::NoGroupExistsErr => {
-                                        println!("There is no group with name: {}", &parent_group);
-                                    }
-                                    GroupError::InputOutsideOfBoundsError => {
-                                        println!("Please enter a valid string with length 1-50");
-                                    }
-                                },
+                                Err(e) => println!(
+                                    "{}",
+                                    add_bird_error_message(
+                                        e,
+                                        &parent_group,
+                                        &name,
+                                        &scientific_name
+                                    )
+                                ),
                             }
                         }
                     }
                 }
             }
-            // exit the program
             6 => {
-                file::save_tree(&tree);
-                break;
+                // remove a bird
+                println!("Enter the name of the bird to remove:");
+                if let Some(name) = prompt.read::<String>(Completion::Birds) {
+                    match tree.remove_bird(&name) {
+                        Ok(()) => println!("Removed {}\n", &name),
+                        Err(e) => print_group_error(e, &name),
+                    }
+                }
+            }
+            7 => {
+                // remove a group
+                println!("Enter the group to remove:");
+                if let Some(group_name) = prompt.read::<String>(Completion::Groups) {
+                    println!("Also remove every bird and group inside it? (y/n)");
+                    if let Some(answer) = prompt.read::<String>(Completion::Nothing) {
+                        let mode = if answer.eq_ignore_ascii_case("y") {
+                            RemoveMode::Cascade
+                        } else {
+                            RemoveMode::EmptyOnly
+                        };
+                        match tree.remove_group(&group_name, mode) {
+                            Ok(()) => println!("Removed {}\n", &group_name),
+                            Err(e) => print_group_error(e, &group_name),
+                        }
+                    }
+                }
             }
-            _ => println!("Please enter a number in range (1-6)"),
+            8 => {
+                // move a bird or group to a different parent group
+                println!("Are you moving a species or a classification? (s/c)");
+                if let Some(kind) = prompt.read::<String>(Completion::Nothing) {
+                    let completion = if kind.eq_ignore_ascii_case("s") {
+                        Completion::Birds
+                    } else {
+                        Completion::Groups
+                    };
+                    println!("Enter the name of the species or classification to move:");
+                    if let Some(name) = prompt.read::<String>(completion) {
+                        println!("Enter the new parent group");
+                        if let Some(parent_group) = prompt.read::<String>(Completion::Groups) {
+                            let result = if kind.eq_ignore_ascii_case("s") {
+                                tree.move_bird(&name, &parent_group)
+                            } else {
+                                tree.move_group(&name, &parent_group)
+                            };
+                            match result {
+                                Ok(()) => println!("Moved {}, to {}\n", &name, &parent_group),
+                                Err(GroupError::NoGroupExistsErr) => println!(
+                                    "There is no group with name: {} or {}",
+                                    &name, &parent_group
+                                ),
+                                Err(e) => print_group_error(e, &name),
+                            }
+                        }
+                    }
+                }
+            }
+            9 => {
+                // rename a bird or group
+                println!(
+                    "Are you changing a common name, a scientific name or a classification? (n/s/c)"
+                );
+                if let Some(kind) = prompt.read::<String>(Completion::Nothing) {
+                    let completion = match kind.to_lowercase().as_str() {
+                        "n" | "s" => Completion::Birds,
+                        _ => Completion::Groups,
+                    };
+                    println!("Enter the common name of the species, or the classification:");
+                    if let Some(name) = prompt.read::<String>(completion) {
+                        println!("Enter the new name");
+                        if let Some(new_name) = prompt.read::<String>(Completion::Nothing) {
+                            let result = match kind.to_lowercase().as_str() {
+                                "n" => tree.rename_bird(&name, &new_name),
+                                "s" => tree.rename_scientific_name(&name, &new_name),
+                                _ => tree.rename_group(&name, &new_name),
+                            };
+                            match result {
+                                Ok(()) => println!("Renamed {}, to {}\n", &name, &new_name),
+                                Err(e) => print_group_error(e, &name),
+                            }
+                        }
+                    }
+                }
+            }
+            10 => {
+                // show the hierarchy below a group
+                println!("Enter the group to show, or leave this blank for the whole tree:");
+                if let Some(group_name) = prompt.read::<String>(Completion::Groups) {
+                    let start = match group_name.as_str() {
+                        "" => Some(tree.root()),
+                        group_name => tree.get_group_with_name(group_name),
+                    };
+                    println!("How many levels should be shown? Leave this blank for all of them");
+                    if let Some(max_depth) = prompt.read::<String>(Completion::Nothing) {
+                        println!("Show how many species are in each group? (y/n)");
+                        if let Some(counts) = prompt.read::<String>(Completion::Nothing) {
+                            match start {
+                                Some(start) => {
+                                    let options = TextTreeOptions {
+                                        max_depth: max_depth.parse().ok(),
+                                        counts: counts.eq_ignore_ascii_case("y"),
+                                        unicode: supports_unicode(),
+                                    };
+                                    println!("\n{}", diagram::to_text_tree(&tree, start, &options));
+                                }
+                                None => {
+                                    println!("There is no group with name: {}", &group_name)
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            // exit the program
+            11 => {
+                match file::save_tree(&tree, &config.data_path, config.backup_count) {
+                    Ok(()) => break,
+                    Err(e) => {
+                        eprintln!(
+                            "Failed to save birds to {}: {}",
+                            config.data_path.display(),
+                            e
+                        );
+                        // the input has ended, so asking again would only fail again
+                        if exit_pressed {
+                            eprintln!("Your changes have not been saved.");
+                            prompt.save_history();
+                            return ExitCode::FAILURE;
+                        }
+                        // stay in the loop so that unsaved changes are not lost
+                        eprintln!("Your changes have not been saved, please try again.\n");
+                    }
+                }
+            }
+            _ => println!("Please enter a number in range (1-11)"),
         }
+
+        // Ctrl-D part way through a task saves and exits as well
+        exit_pressed = prompt.take_interrupt() == Some(Interrupt::Exit);
     }
+
+    prompt.save_history();
+
+    ExitCode::SUCCESS
+}
+
+fn main() -> ExitCode {
+    let (config, args) = match Config::load(env::args().skip(1)) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            eprintln!("Run formative --help to see every option");
+            return ExitCode::from(2);
+        }
+    };
+
+    // run a single command if one was given
+    let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+    if let Some(code) = commands::run(&config, &args) {
+        return code;
+    }
+
+    // build the tree from json, or the built in taxonomy
+    let Ok(tree) = ui::load_tree(&config, true) else {
+        return ExitCode::FAILURE;
+    };
+
+    let mut prompt = match Prompt::new(config::history_path()) {
+        Ok(prompt) => prompt,
+        Err(e) => {
+            eprintln!("Error: could not set up the terminal: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    run_menu(&config, tree, &mut prompt)
 }
@@ -0,0 +1,743 @@
+use crate::birds::{BirdTree, GroupError, Node, NodeId, Rank};
+use crate::ui::{add_bird_error_message, add_group_error_message};
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Characters that can't be part of an unquoted Newick label.
+const RESERVED: [char; 9] = ['(', ')', '[', ']', '\'', ':', ';', ',', '_'];
+
+/// Write a name as a Newick label. Spaces become underscores, and names with anything else that
+/// Newick gives a meaning to are quoted.
+fn label(name: &str) -> String {
+    if !name.is_empty()
+        && !name
+            .chars()
+            .any(|c| RESERVED.contains(&c) || (c.is_whitespace() && c != ' '))
+    {
+        name.replace(' ', "_")
+    } else {
+        format!("'{}'", name.replace('\'', "''"))
+    }
+}
+
+/// Write a value inside a Newick annotation comment.
+fn annotation_value(value: &str) -> String {
+    format!(
+        "\"{}\"",
+        value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace(']', "\\]")
+    )
+}
+
+/// Write a node and everything inside it in Newick format. Returns false and writes nothing for
+/// groups without any birds in them, as Newick can't tell an empty group apart from a bird.
+fn write_node(tree: &BirdTree, id: NodeId, label_groups: bool, out: &mut String) -> bool {
+    match tree.get(id) {
+        Some(Node::Group { name, rank }) => {
+            let mut children = vec![];
+            for child in tree.children(id).iter() {
+                let mut child_out = String::new();
+                if write_node(tree, *child, label_groups, &mut child_out) {
+                    children.push(child_out);
+                }
+            }
+            if children.is_empty() {
+                return false;
+            }
+
+            out.push('(');
+            out.push_str(&children.join(","));
+            out.push(')');
+            if label_groups {
+                out.push_str(&label(name));
+                if let Some(rank) = rank {
+                    out.push_str(&format!("[&rank={}]", rank));
+                }
+            }
+            true
+        }
+        Some(Node::Bird { name, .. }) => {
+            let scientific_name = tree
+                .binomial_name(id)
+                .map(|name| name.to_string())
+                .unwrap_or_default();
+            out.push_str(&label(&scientific_name));
+            out.push_str(&format!("[&commonName={}]", annotation_value(name)));
+            true
+        }
+        None => false,
+    }
+}
+
+/// Write a whole tree in Newick format, starting at the root. Birds are labelled with their
+/// scientific name, and their common name is kept in an annotation comment. Groups are only
+/// labelled when `label_groups` is set, and groups without any birds in them are left out.
+pub fn to_newick(tree: &BirdTree, label_groups: bool) -> String {
+    let mut out = String::new();
+    if !write_node(tree, tree.root(), label_groups, &mut out) {
+        // a tree without birds is just its root
+        out.push_str(&label(
+            tree.get(tree.root()).map(Node::name).unwrap_or_default(),
+        ));
+    }
+    out.push(';');
+
+    out
+}
+
+/// Write a whole tree as a NEXUS file with a single tree in its trees block.
+pub fn to_nexus(tree: &BirdTree, label_groups: bool) -> String {
+    let name = tree.get(tree.root()).map(Node::name).unwrap_or_default();
+
+    format!(
+        "#NEXUS\n\nBEGIN TREES;\n\tTREE {} = [&R] {}\nEND;\n",
+        label(name),
+        to_newick(tree, label_groups)
+    )
+}
+
+/// An error that occurred while reading a Newick file.
+#[derive(Debug)]
+pub enum NewickError {
+    /// The file could not be read.
+    Read(PathBuf, io::Error),
+    /// The file is not valid Newick. The position counts characters from 1.
+    Syntax {
+        path: PathBuf,
+        position: usize,
+        message: String,
+    },
+    /// A NEXUS file has no tree in it.
+    NoTree(PathBuf),
+    /// The tree is a single bird, with no group to put it in.
+    NoGroups(PathBuf),
+    /// The tree could not be built from the file, with the reason a node couldn't be added.
+    Tree(PathBuf, String),
+}
+
+impl fmt::Display for NewickError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NewickError::Read(path, e) => write!(f, "could not read {}: {}", path.display(), e),
+            NewickError::Syntax {
+                path,
+                position,
+                message,
+            } => write!(
+                f,
+                "{} is not valid Newick at character {}: {}",
+                path.display(),
+                position,
+                message
+            ),
+            NewickError::NoTree(path) => write!(f, "{} has no trees block", path.display()),
+            NewickError::NoGroups(path) => {
+                write!(f, "{} has a single bird and no groups", path.display())
+            }
+            NewickError::Tree(path, reason) => {
+                write!(
+                    f,
+                    "could not build a tree from {}: {}",
+                    path.display(),
+                    reason
+                )
+            }
+        }
+    }
+}
+
+/// A node read from a Newick string, before it is added to a tree.
+struct NewickNode {
+    label: Option<String>,
+    /// The `key=value` pairs from `[&...]` comments after the node
+    annotations: Vec<(String, String)>,
+    children: Vec<NewickNode>,
+}
+
+impl NewickNode {
+    /// Get the value of an annotation, ignoring the case of the key.
+    fn annotation(&self, key: &str) -> Option<&str> {
+        self.annotations
+            .iter()
+            .find(|(other, _)| other.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Reads a Newick string one character at a time.
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn error(&self, message: &str) -> (usize, String) {
+        (self.pos + 1, message.to_string())
+    }
+
+    /// Skip whitespace and comments, collecting any annotations from the comments.
+    fn skip_space(
+        &mut self,
+        annotations: &mut Vec<(String, String)>,
+    ) -> Result<(), (usize, String)> {
+        loop {
+            match self.peek() {
+                Some(c) if c.is_whitespace() => self.pos += 1,
+                Some('[') => {
+                    self.pos += 1;
+                    let comment = self.comment()?;
+                    if let Some(pairs) = comment.strip_prefix('&') {
+                        annotations.extend(parse_annotations(pairs));
+                    }
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    /// Read a comment after its opening bracket, up to and including the closing bracket.
+    /// Brackets inside double quotes or after a backslash don't end the comment.
+    fn comment(&mut self) -> Result<String, (usize, String)> {
+        // errors point at the opening bracket
+        let start = self.pos - 1;
+        let mut text = String::new();
+        let mut in_quotes = false;
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            match c {
+                '\\' => {
+                    text.push(c);
+                    text.extend(self.peek());
+                    self.pos += 1;
+                }
+                '"' => {
+                    in_quotes = !in_quotes;
+                    text.push(c);
+                }
+                ']' if !in_quotes => return Ok(text),
+                _ => text.push(c),
+            }
+        }
+
+        self.pos = start;
+        Err(self.error("a comment is never closed"))
+    }
+
+    /// Read a label, which is either quoted or ends at the next reserved character.
+    fn label(&mut self) -> Result<Option<String>, (usize, String)> {
+        if self.peek() == Some('\'') {
+            let start = self.pos;
+            self.pos += 1;
+            let mut label = String::new();
+            loop {
+                match self.peek() {
+                    Some('\'') if self.chars.get(self.pos + 1) == Some(&'\'') => {
+                        label.push('\'');
+                        self.pos += 2;
+                    }
+                    Some('\'') => {
+                        self.pos += 1;
+                        return Ok(Some(label));
+                    }
+                    Some(c) => {
+                        label.push(c);
+                        self.pos += 1;
+                    }
+                    None => {
+                        self.pos = start;
+                        return Err(self.error("a quoted label is never closed"));
+                    }
+                }
+            }
+        }
+
+        let mut label = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() || (RESERVED.contains(&c) && c != '_') {
+                break;
+            }
+            // underscores in unquoted labels stand for spaces
+            label.push(if c == '_' { ' ' } else { c });
+            self.pos += 1;
+        }
+
+        Ok((!label.is_empty()).then_some(label))
+    }
+
+    /// Skip a branch length, which is ignored.
+    fn branch_length(&mut self) {
+        if self.peek() == Some(':') {
+            self.pos += 1;
+            while let Some(c) = self.peek() {
+                if c.is_whitespace() || ",;()[".contains(c) {
+                    break;
+                }
+                self.pos += 1;
+            }
+        }
+    }
+
+    /// Read a node and everything inside it.
+    fn node(&mut self) -> Result<NewickNode, (usize, String)> {
+        let mut annotations = vec![];
+        self.skip_space(&mut annotations)?;
+
+        let mut children = vec![];
+        if self.peek() == Some('(') {
+            self.pos += 1;
+            loop {
+                children.push(self.node()?);
+                self.skip_space(&mut annotations)?;
+                match self.peek() {
+                    Some(',') => self.pos += 1,
+                    Some(')') => {
+                        self.pos += 1;
+                        break;
+                    }
+                    _ => return Err(self.error("expected , or ) after a node")),
+                }
+            }
+        }
+
+        self.skip_space(&mut annotations)?;
+        let label = self.label()?;
+        self.skip_space(&mut annotations)?;
+        self.branch_length();
+        self.skip_space(&mut annotations)?;
+
+        if children.is_empty() && label.is_none() {
+            return Err(self.error("a node outside of brackets needs a label"));
+        }
+
+        Ok(NewickNode {
+            label,
+            annotations,
+            children,
+        })
+    }
+}
+
+/// Parse the `key=value` pairs of an annotation comment, which are separated by commas.
+/// Values can be in double quotes to hold commas.
+fn parse_annotations(text: &str) -> Vec<(String, String)> {
+    let mut pairs = vec![];
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => current.extend(chars.next()),
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => pairs.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    pairs.push(current);
+
+    pairs
+        .into_iter()
+        .filter_map(|pair| {
+            let (key, value) = pair.split_once('=')?;
+            Some((key.trim().to_string(), value.trim().to_string()))
+        })
+        .collect()
+}
+
+/// Parse a single Newick tree, which must end with a semicolon.
+fn parse_newick(text: &str) -> Result<NewickNode, (usize, String)> {
+    let mut parser = Parser {
+        chars: text.chars().collect(),
+        pos: 0,
+    };
+
+    let root = parser.node()?;
+    parser.skip_space(&mut vec![])?;
+    if parser.peek() != Some(';') {
+        return Err(parser.error("expected ; at the end of the tree"));
+    }
+
+    Ok(root)
+}
+
+/// Find the first tree in a NEXUS file and return the text of it, or None if there is no tree.
+/// Returns how many characters into the file the tree starts, so that errors point at the
+/// right place.
+fn nexus_tree(text: &str) -> Option<(usize, &str)> {
+    // only ASCII is lowercased so that positions in `lower` are the same as in `text`
+    let lower = text.to_ascii_lowercase();
+    let block = lower.find("begin trees;")?;
+    let tree = block + lower[block..].find("tree ")? + "tree ".len();
+    let start = tree + text[tree..].find('=')? + 1;
+
+    Some((text[..start].chars().count(), &text[start..]))
+}
+
+/// Get the name of a node, or an empty string if it isn't in the tree.
+fn node_name(tree: &BirdTree, id: NodeId) -> &str {
+    tree.get(id).map(Node::name).unwrap_or_default()
+}
+
+/// Add a parsed node to a group in the tree, along with everything inside it.
+/// `unnamed` counts the groups that have been named so far because they had no label.
+/// Returns the reason a node couldn't be added on failure.
+fn add_node(
+    tree: &mut BirdTree,
+    parent: NodeId,
+    node: &NewickNode,
+    unnamed: &mut usize,
+) -> Result<(), String> {
+    if node.children.is_empty() {
+        let scientific_name = node.label.clone().unwrap_or_default();
+        let common_name = node.annotation("commonName").unwrap_or(&scientific_name);
+
+        let (parent, epithet) = bird_parent(tree, parent, &scientific_name)?;
+        tree.add(parent, Node::new_bird(common_name, epithet))
+            .map_err(|e| {
+                add_bird_error_message(e, node_name(tree, parent), common_name, epithet)
+            })?;
+        return Ok(());
+    }
+
+    let group = group_node(node, unnamed);
+    let group = add_group(tree, parent, group.name(), group.rank())?;
+    for child in node.children.iter() {
+        add_node(tree, group, child, unnamed)?;
+    }
+
+    Ok(())
+}
+
+/// Add a group to the tree. A rank out of order is dropped rather than losing the group.
+fn add_group(
+    tree: &mut BirdTree,
+    parent: NodeId,
+    name: &str,
+    rank: Option<Rank>,
+) -> Result<NodeId, String> {
+    match tree.add(parent, Node::new_group(name, rank)) {
+        Err(GroupError::RankOrderError { .. }) => tree.add(parent, Node::new_group(name, None)),
+        group => group,
+    }
+    .map_err(|e| add_group_error_message(e, node_name(tree, parent), name))
+}
+
+/// Get the group called `name` directly inside `parent`, adding it with `rank` if it isn't
+/// there yet.
+fn child_group(
+    tree: &mut BirdTree,
+    parent: NodeId,
+    name: &str,
+    rank: Rank,
+) -> Result<NodeId, String> {
+    match tree.get_child_group(parent, name) {
+        Some(group) => Ok(group),
+        None => add_group(tree, parent, name, Some(rank)),
+    }
+}
+
+/// Get the name of the genus that a bird in `group` takes, which is the group itself when
+/// there is no genus above it.
+fn genus_name(tree: &BirdTree, group: NodeId) -> &str {
+    let genus = tree.ancestor_with_rank(group, Rank::Genus).unwrap_or(group);
+    node_name(tree, genus)
+}
+
+/// Work out which group a bird read with a scientific name label belongs in, and the epithet
+/// it stores. Birds only store their last epithet and take the rest of their name from the
+/// groups above them, so a genus or species in the label that those groups don't already
+/// supply gets a group inside `parent`, which is added if it isn't there yet.
+fn bird_parent<'a>(
+    tree: &mut BirdTree,
+    parent: NodeId,
+    label: &'a str,
+) -> Result<(NodeId, &'a str), String> {
+    match label.split_whitespace().collect::<Vec<_>>().as_slice() {
+        [] | [_] => Ok((parent, label)),
+        [genus, species, subspecies] => {
+            // subspecies sit inside a group for their species
+            let in_species = tree
+                .get(parent)
+                .is_some_and(|node| node.rank() == Some(Rank::Species));
+            let above = match in_species {
+                true => tree.parent(parent).unwrap_or(parent),
+                false => parent,
+            };
+            if in_species
+                && node_name(tree, parent).eq_ignore_ascii_case(species)
+                && genus_name(tree, above).eq_ignore_ascii_case(genus)
+            {
+                return Ok((parent, subspecies));
+            }
+
+            let genus = genus_group(tree, above, genus)?;
+            Ok((
+                child_group(tree, genus, species, Rank::Species)?,
+                subspecies,
+            ))
+        }
+        _ => {
+            let (genus, species) = label.split_once(' ').unwrap_or_default();
+            Ok((genus_group(tree, parent, genus)?, species))
+        }
+    }
+}
+
+/// Get the group that a bird in `parent` with the given genus should go in. This is `parent`
+/// when the bird would already take its genus from it, then the genus if it is already in the
+/// tree, otherwise a genus group inside `parent`, which is added if it isn't there yet.
+fn genus_group(tree: &mut BirdTree, parent: NodeId, genus: &str) -> Result<NodeId, String> {
+    if genus_name(tree, parent).eq_ignore_ascii_case(genus) {
+        return Ok(parent);
+    }
+    // group names are unique, so a genus elsewhere in the tree must be the same genus
+    if let Some(group) = tree
+        .get_group_with_name(genus)
+        .filter(|group| tree.get(*group).and_then(Node::rank) == Some(Rank::Genus))
+    {
+        return Ok(group);
+    }
+
+    child_group(tree, parent, genus, Rank::Genus)
+}
+
+/// Make a group node from a parsed node, naming it if it has no label.
+fn group_node(node: &NewickNode, unnamed: &mut usize) -> Node {
+    let rank = node
+        .annotation("rank")
+        .and_then(|rank| rank.parse::<Rank>().ok());
+    let name = match &node.label {
+        Some(label) => label.clone(),
+        None => {
+            *unnamed += 1;
+            format!("Unnamed clade {}", unnamed)
+        }
+    };
+
+    Node::new_group(&name, rank)
+}
+
+/// Read a tree from a Newick file, or the first tree in a NEXUS file.
+/// Nodes with children become groups, and nodes without children become birds. Groups without
+/// a label are given one, and birds without a `commonName` annotation use their label for both
+/// names. Branch lengths are ignored.
+pub fn read_newick(path: &Path) -> Result<BirdTree, NewickError> {
+    let text = fs::read_to_string(path).map_err(|e| NewickError::Read(path.to_path_buf(), e))?;
+
+    let (offset, newick) = if text.trim_start().to_lowercase().starts_with("#nexus") {
+        nexus_tree(&text).ok_or_else(|| NewickError::NoTree(path.to_path_buf()))?
+    } else {
+        (0, text.as_str())
+    };
+    let root = parse_newick(newick).map_err(|(position, message)| NewickError::Syntax {
+        path: path.to_path_buf(),
+        position: offset + position,
+        message,
+    })?;
+
+    if root.children.is_empty() {
+        return Err(NewickError::NoGroups(path.to_path_buf()));
+    }
+    let mut unnamed = 0;
+    let mut tree = BirdTree::new(group_node(&root, &mut unnamed))
+        .expect("A tree can always be made from a group");
+    let tree_root = tree.root();
+    for child in root.children.iter() {
+        add_node(&mut tree, tree_root, child, &mut unnamed)
+            .map_err(|reason| NewickError::Tree(path.to_path_buf(), reason))?;
+    }
+
+    Ok(tree)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file::seed_tree;
+    use crate::testing::{TestDir, bird_names};
+
+    #[test]
+    fn labels_are_quoted_when_needed() {
+        assert_eq!(label("Kea"), "Kea");
+        assert_eq!(label("New Zealand bellbird"), "New_Zealand_bellbird");
+        assert_eq!(label("snake_case"), "'snake_case'");
+        assert_eq!(label("O'Brien's (parrot)"), "'O''Brien''s (parrot)'");
+        assert_eq!(label("tab\there"), "'tab\there'");
+        assert_eq!(label(""), "''");
+    }
+
+    #[test]
+    fn parser_reads_quotes_comments_and_branch_lengths() {
+        let root = parse_newick(
+            "[a tree] ('O''Brien''s_(parrot)'[&commonName=\"Kākā, [bush] parrot\"]:0.5,\
+             Nestor_notabilis [plain comment] :1e-3)'Nestor'[&rank=genus];",
+        )
+        .unwrap();
+
+        assert_eq!(root.label.as_deref(), Some("Nestor"));
+        assert_eq!(root.annotation("RANK"), Some("genus"));
+        let [first, second] = root.children.as_slice() else {
+            panic!("expected two children");
+        };
+        // underscores are only spaces outside of quotes
+        assert_eq!(first.label.as_deref(), Some("O'Brien's_(parrot)"));
+        assert_eq!(first.annotation("commonName"), Some("Kākā, [bush] parrot"));
+        assert_eq!(second.label.as_deref(), Some("Nestor notabilis"));
+        assert!(second.annotations.is_empty());
+    }
+
+    #[test]
+    fn parser_reports_where_errors_are() {
+        let error = |text| parse_newick(text).err().unwrap();
+
+        // positions count characters from 1
+        assert_eq!(error("(Kea,'Kaka);").0, 6);
+        assert_eq!(error("(Kea[never closed);").0, 5);
+        assert_eq!(error("(Kea,Kaka)").1, "expected ; at the end of the tree");
+        assert_eq!(
+            error("(Kea,);").1,
+            "a node outside of brackets needs a label"
+        );
+    }
+
+    #[test]
+    fn labelled_export_round_trips() {
+        let dir = TestDir::new("labelled_export_round_trips");
+        let seed = seed_tree();
+        let newick = to_newick(&seed, true);
+        let path = dir.file("labelled.nwk", &newick);
+
+        let tree = read_newick(&path).unwrap();
+
+        assert_eq!(to_newick(&tree, true), newick);
+        assert_eq!(bird_names(&tree), bird_names(&seed));
+    }
+
+    #[test]
+    fn unlabelled_export_keeps_scientific_names() {
+        let dir = TestDir::new("unlabelled_export_keeps_scientific_names");
+        let seed = seed_tree();
+        let path = dir.file("unlabelled.nwk", &to_newick(&seed, false));
+
+        let tree = read_newick(&path).unwrap();
+
+        assert_eq!(bird_names(&tree), bird_names(&seed));
+        assert!(tree.get_group_with_name("Unnamed clade 1").is_some());
+    }
+
+    /// The seed birds with a subspecies of the kākā, which sits inside a species group.
+    fn tree_with_subspecies() -> BirdTree {
+        let mut tree = seed_tree();
+        tree.add_group("Nestor", "sub", Some(Rank::Species))
+            .unwrap();
+        tree.add_bird("sub", "North Island kākā", "septentrionalis")
+            .unwrap();
+        tree
+    }
+
+    #[test]
+    fn subspecies_round_trip() {
+        let dir = TestDir::new("subspecies_round_trip");
+        let tree = tree_with_subspecies();
+        let names = bird_names(&tree);
+        assert!(names.contains(&(
+            "North Island kākā".to_string(),
+            "Nestor sub septentrionalis".to_string()
+        )));
+
+        for (name, label_groups) in [("labelled.nwk", true), ("unlabelled.nwk", false)] {
+            let path = dir.file(name, &to_newick(&tree, label_groups));
+            let read = read_newick(&path).unwrap();
+
+            assert_eq!(bird_names(&read), names);
+            let bird = read.search_by_name("North Island kākā").unwrap();
+            assert_eq!(read.get(bird).unwrap().scientific_name(), "septentrionalis");
+        }
+    }
+
+    #[test]
+    fn binomial_labels_are_put_in_their_genus() {
+        let dir = TestDir::new("binomial_labels_are_put_in_their_genus");
+        let path = dir.file("genus.nwk", "(Foo_bar,Baz_qux,Baz_quux)Foo;");
+
+        let tree = read_newick(&path).unwrap();
+
+        assert_eq!(
+            bird_names(&tree),
+            vec![
+                ("Foo bar".to_string(), "Foo bar".to_string()),
+                ("Baz qux".to_string(), "Baz qux".to_string()),
+                ("Baz quux".to_string(), "Baz quux".to_string()),
+            ]
+        );
+        let genus = tree.get_group_with_name("Baz").unwrap();
+        assert_eq!(tree.get(genus).unwrap().rank(), Some(Rank::Genus));
+        assert_eq!(tree.children(genus).len(), 2);
+    }
+
+    #[test]
+    fn tree_errors_are_readable() {
+        let dir = TestDir::new("tree_errors_are_readable");
+        let path = dir.file(
+            "dup.nwk",
+            "(Nestor_notabilis[&commonName=\"Kea\"],Nestor_other[&commonName=\"Kea\"])Nestor;",
+        );
+
+        let Err(error) = read_newick(&path) else {
+            panic!("expected the second Kea to be rejected");
+        };
+
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "could not build a tree from {}: There is already a bird called Kea, \
+                 or a bird named other in Nestor",
+                path.display()
+            )
+        );
+    }
+
+    #[test]
+    fn nexus_export_round_trips() {
+        let dir = TestDir::new("nexus_export_round_trips");
+        let seed = seed_tree();
+        let path = dir.file("seed.nex", &to_nexus(&seed, true));
+
+        let tree = read_newick(&path).unwrap();
+
+        assert_eq!(bird_names(&tree), bird_names(&seed));
+    }
+
+    #[test]
+    fn nexus_text_before_the_tree_can_be_any_case() {
+        let dir = TestDir::new("nexus_text_before_the_tree_can_be_any_case");
+        // 'İ' is longer in bytes once lowercased, which must not move where the tree is found
+        let text =
+            "#nexus\n[İİİİ Tūī]\nbegin Trees;\n\tTree kea = (Nestor_notabilis)Nestor;\nEnd;\n";
+        let path = dir.file("case.nex", text);
+
+        let tree = read_newick(&path).unwrap();
+        assert_eq!(
+            bird_names(&tree),
+            vec![(
+                "Nestor notabilis".to_string(),
+                "Nestor notabilis".to_string()
+            )]
+        );
+
+        let broken = text.replace("notabilis)", "notabilis");
+        let path = dir.file("broken.nex", &broken);
+        let Err(NewickError::Syntax { position, .. }) = read_newick(&path) else {
+            panic!("expected a syntax error");
+        };
+        assert_eq!(broken.chars().nth(position - 1), Some(';'));
+    }
+}
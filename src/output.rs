@@ -0,0 +1,273 @@
+use serde::Serialize;
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::birds::{BirdTree, Node, NodeId, Rank};
+
+/// How the results of a command are printed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Readable text, the same as the menu shows
+    Text,
+    /// json records, for other programs
+    Json,
+    /// Tab separated values with a header row, for spreadsheets and shell tools
+    Tsv,
+}
+
+impl OutputFormat {
+    /// Every output format, in the order they should be shown to the user.
+    pub const ALL: [OutputFormat; 3] = [OutputFormat::Text, OutputFormat::Json, OutputFormat::Tsv];
+
+    /// Get the name of the output format.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OutputFormat::Text => "text",
+            OutputFormat::Json => "json",
+            OutputFormat::Tsv => "tsv",
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseOutputFormatError;
+
+impl FromStr for OutputFormat {
+    type Err = ParseOutputFormatError;
+
+    /// Parse an output format from its name, ignoring case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        OutputFormat::ALL
+            .into_iter()
+            .find(|format| format.as_str().eq_ignore_ascii_case(s))
+            .ok_or(ParseOutputFormatError)
+    }
+}
+
+/// A group above a bird.
+#[derive(Serialize)]
+struct GroupRecord<'a> {
+    name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rank: Option<Rank>,
+}
+
+/// A bird, with everything about it that can be found from the tree.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BirdRecord<'a> {
+    common_name: &'a str,
+    /// The binomial or trinomial name
+    scientific_name: String,
+    /// The species (or subspecies) epithet that the bird stores
+    epithet: &'a str,
+    /// The groups above the bird, starting at the root
+    lineage: Vec<GroupRecord<'a>>,
+}
+
+/// A node of a tree dump, holding everything below it.
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+enum TreeRecord<'a> {
+    #[serde(rename_all = "camelCase")]
+    Group {
+        name: &'a str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        rank: Option<Rank>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        species_count: Option<usize>,
+        /// Left out when the depth limit hides the children of the group
+        #[serde(skip_serializing_if = "Option::is_none")]
+        children: Option<Vec<TreeRecord<'a>>>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Bird {
+        common_name: &'a str,
+        scientific_name: String,
+    },
+}
+
+/// Get the full scientific name of a bird, or the name it stores if there's no genus above it.
+fn scientific_name(tree: &BirdTree, id: NodeId) -> String {
+    match tree.binomial_name(id) {
+        Some(name) => name.to_string(),
+        None => tree
+            .get(id)
+            .map(|node| node.scientific_name().to_string())
+            .unwrap_or_default(),
+    }
+}
+
+fn bird_record(tree: &BirdTree, id: NodeId) -> Option<BirdRecord<'_>> {
+    let Some(Node::Bird {
+        name,
+        scientific_name: epithet,
+    }) = tree.get(id)
+    else {
+        return None;
+    };
+
+    let lineage = tree
+        .lineage(id)
+        .into_iter()
+        .filter_map(|group| {
+            let group = tree.get(group)?;
+            Some(GroupRecord {
+                name: group.name(),
+                rank: group.rank(),
+            })
+        })
+        .collect();
+
+    Some(BirdRecord {
+        common_name: name,
+        scientific_name: scientific_name(tree, id),
+        epithet,
+        lineage,
+    })
+}
+
+/// Write a single bird as a json object.
+pub fn bird_to_json(tree: &BirdTree, bird: NodeId) -> String {
+    let record = bird_record(tree, bird);
+    serde_json::to_string_pretty(&record).expect("Records can always be converted to json") + "\n"
+}
+
+/// Write a list of birds as a json array of objects.
+pub fn birds_to_json(tree: &BirdTree, birds: &[NodeId]) -> String {
+    let records = birds
+        .iter()
+        .filter_map(|bird| bird_record(tree, *bird))
+        .collect::<Vec<_>>();
+    serde_json::to_string_pretty(&records).expect("Records can always be converted to json") + "\n"
+}
+
+/// Escape a field of a TSV file, so that tabs and line breaks in names don't break the layout.
+fn tsv_field(field: &str) -> String {
+    field
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+/// Write a list of birds as TSV, with a header row. The lineage is the names of the groups
+/// above each bird joined with " > ".
+pub fn birds_to_tsv(tree: &BirdTree, birds: &[NodeId]) -> String {
+    let mut out = String::from("commonName\tscientificName\tepithet\tlineage\n");
+    for record in birds.iter().filter_map(|bird| bird_record(tree, *bird)) {
+        let lineage = record
+            .lineage
+            .iter()
+            .map(|group| group.name)
+            .collect::<Vec<_>>()
+            .join(" > ");
+        let fields = [
+            record.common_name,
+            &record.scientific_name,
+            record.epithet,
+            &lineage,
+        ];
+        out.push_str(&fields.map(tsv_field).join("\t"));
+        out.push('\n');
+    }
+
+    out
+}
+
+/// Build the record of a node and everything below it, down to `max_depth` levels.
+fn tree_record(
+    tree: &BirdTree,
+    id: NodeId,
+    max_depth: Option<usize>,
+    counts: bool,
+) -> Option<TreeRecord<'_>> {
+    match tree.get(id)? {
+        Node::Group { name, rank } => Some(TreeRecord::Group {
+            name,
+            rank: *rank,
+            species_count: counts.then(|| tree.count_birds(id)),
+            children: match max_depth {
+                Some(0) => None,
+                _ => Some(
+                    tree.children(id)
+                        .iter()
+                        .filter_map(|child| {
+                            tree_record(tree, *child, max_depth.map(|max| max - 1), counts)
+                        })
+                        .collect(),
+                ),
+            },
+        }),
+        Node::Bird { name, .. } => Some(TreeRecord::Bird {
+            common_name: name,
+            scientific_name: scientific_name(tree, id),
+        }),
+    }
+}
+
+/// Write a node and everything below it as nested json objects, down to `max_depth` levels.
+/// Groups have a `speciesCount` when `counts` is set.
+pub fn tree_to_json(tree: &BirdTree, id: NodeId, max_depth: Option<usize>, counts: bool) -> String {
+    let record = tree_record(tree, id, max_depth, counts);
+    serde_json::to_string_pretty(&record).expect("Records can always be converted to json") + "\n"
+}
+
+/// Write a node and everything below it as TSV, with a row for each node in the order they are
+/// found from the top down, down to `max_depth` levels. The path is the names of the groups above
+/// each node joined with " > ". Groups have a species count when `counts` is set.
+pub fn tree_to_tsv(tree: &BirdTree, id: NodeId, max_depth: Option<usize>, counts: bool) -> String {
+    let mut out = String::from("depth\tkind\tname\trank\tscientificName\tspeciesCount\tpath\n");
+
+    let start_depth = tree.lineage(id).len();
+    for node in tree.preorder(id) {
+        let lineage = tree.lineage(node);
+        let depth = lineage.len() - start_depth;
+        if max_depth.is_some_and(|max| depth > max) {
+            continue;
+        }
+
+        let path = lineage
+            .iter()
+            .filter_map(|group| Some(tree.get(*group)?.name()))
+            .collect::<Vec<_>>()
+            .join(" > ");
+        let row = match tree.get(node) {
+            Some(Node::Group { name, rank }) => [
+                "group".to_string(),
+                name.clone(),
+                rank.map(|rank| rank.to_string()).unwrap_or_default(),
+                String::new(),
+                match counts {
+                    true => tree.count_birds(node).to_string(),
+                    false => String::new(),
+                },
+            ],
+            Some(Node::Bird { name, .. }) => [
+                "bird".to_string(),
+                name.clone(),
+                String::new(),
+                scientific_name(tree, node),
+                String::new(),
+            ],
+            None => continue,
+        };
+
+        out.push_str(&depth.to_string());
+        for field in row.iter().chain([&path]) {
+            out.push('\t');
+            out.push_str(&tsv_field(field));
+        }
+        out.push('\n');
+    }
+
+    out
+}
@@ -0,0 +1,209 @@
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::FileHistory;
+use rustyline::validate::Validator;
+use rustyline::{Config as EditorConfig, Context, Editor, Helper};
+
+use std::fs;
+use std::io::{IsTerminal, Write, stdin, stdout};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::birds::{BirdTree, Node};
+
+/// How many answers are kept in the history file.
+const HISTORY_SIZE: usize = 1000;
+
+/// The names that can be completed with tab when answering a question.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Completion {
+    /// Nothing is completed, e.g. for numbers and yes/no questions
+    Nothing,
+    Groups,
+    /// The common names of birds
+    Birds,
+    /// The scientific names of birds, e.g. "Nestor meridionalis" or "meridionalis"
+    ScientificNames,
+}
+
+/// A key that stopped a question from being answered.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Interrupt {
+    /// Ctrl-C, which cancels the current task
+    Cancel,
+    /// Ctrl-D or the end of the input, which saves and exits
+    Exit,
+}
+
+/// Completes names from the tree, taken when the menu is shown.
+struct NameCompleter {
+    groups: Vec<String>,
+    birds: Vec<String>,
+    scientific_names: Vec<String>,
+    completion: Completion,
+}
+
+impl Completer for NameCompleter {
+    type Candidate = String;
+
+    /// Complete the whole answer so far, as names can have spaces in them.
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let names: Vec<&String> = match self.completion {
+            Completion::Nothing => vec![],
+            Completion::Groups => self.groups.iter().collect(),
+            Completion::Birds => self.birds.iter().collect(),
+            Completion::ScientificNames => self.scientific_names.iter().collect(),
+        };
+
+        let typed = line[..pos].trim_start().to_lowercase();
+        let mut matches = names
+            .into_iter()
+            .filter(|name| name.to_lowercase().starts_with(&typed))
+            .cloned()
+            .collect::<Vec<_>>();
+        matches.sort_by_key(|name| name.to_lowercase());
+        matches.dedup();
+
+        Ok((0, matches))
+    }
+}
+
+impl Hinter for NameCompleter {
+    type Hint = String;
+}
+
+impl Highlighter for NameCompleter {}
+
+impl Validator for NameCompleter {}
+
+impl Helper for NameCompleter {}
+
+/// Reads answers from the user with line editing, history and completion of names when the
+/// input is a terminal. Input that is piped in is read a line at a time.
+pub struct Prompt {
+    editor: Editor<NameCompleter, FileHistory>,
+    /// Where the history is saved, or None if the input isn't a terminal
+    history_path: Option<PathBuf>,
+    /// The key that stopped the last question from being answered
+    interrupt: Option<Interrupt>,
+}
+
+impl Prompt {
+    /// Set up the editor, loading the history from `history_path` if there is one.
+    pub fn new(history_path: Option<PathBuf>) -> rustyline::Result<Self> {
+        let config = EditorConfig::builder()
+            .max_history_size(HISTORY_SIZE)?
+            .history_ignore_dups(true)?
+            .history_ignore_space(true)
+            .auto_add_history(true)
+            .build();
+        let mut editor = Editor::with_config(config)?;
+        editor.set_helper(Some(NameCompleter {
+            groups: vec![],
+            birds: vec![],
+            scientific_names: vec![],
+            completion: Completion::Nothing,
+        }));
+
+        // keep the history of people using the menu, not of scripts piping answers in
+        let history_path = history_path.filter(|_| stdin().is_terminal());
+        if let Some(path) = &history_path
+            && path.exists()
+            && let Err(e) = editor.load_history(path)
+        {
+            eprintln!("Could not load the history from {}: {}", path.display(), e);
+        }
+
+        Ok(Self {
+            editor,
+            history_path,
+            interrupt: None,
+        })
+    }
+
+    /// Take the names to complete from the tree. This should be done whenever the tree changes.
+    pub fn set_names(&mut self, tree: &BirdTree) {
+        let Some(completer) = self.editor.helper_mut() else {
+            return;
+        };
+        completer.groups.clear();
+        completer.birds.clear();
+        completer.scientific_names.clear();
+
+        for id in tree.preorder(tree.root()) {
+            match tree.get(id) {
+                Some(Node::Group { name, .. }) => completer.groups.push(name.clone()),
+                Some(Node::Bird {
+                    name,
+                    scientific_name,
+                }) => {
+                    completer.birds.push(name.clone());
+                    completer.scientific_names.push(scientific_name.clone());
+                    if let Some(binomial_name) = tree.binomial_name(id) {
+                        completer.scientific_names.push(binomial_name.to_string());
+                    }
+                }
+                None => {}
+            }
+        }
+    }
+
+    /// Returns the user input parsed to the type T, completing the given kind of name.
+    /// Returns None if the input can't be parsed, or if Ctrl-C or Ctrl-D was pressed.
+    pub fn read<T>(&mut self, completion: Completion) -> Option<T>
+    where
+        T: FromStr,
+    {
+        stdout().flush().ok()?;
+        if let Some(completer) = self.editor.helper_mut() {
+            completer.completion = completion;
+        }
+
+        self.interrupt = None;
+        match self.editor.readline("> ") {
+            Ok(line) => line.trim().parse::<T>().ok(),
+            Err(ReadlineError::Interrupted) => {
+                println!("Cancelled");
+                self.interrupt = Some(Interrupt::Cancel);
+                None
+            }
+            Err(ReadlineError::Eof) => {
+                self.interrupt = Some(Interrupt::Exit);
+                None
+            }
+            Err(e) => {
+                eprintln!("Could not read your answer: {}", e);
+                self.interrupt = Some(Interrupt::Exit);
+                None
+            }
+        }
+    }
+
+    /// Take the key that stopped the last question from being answered, if there was one.
+    pub fn take_interrupt(&mut self) -> Option<Interrupt> {
+        self.interrupt.take()
+    }
+
+    /// Save the history for the next time the menu is used.
+    pub fn save_history(&mut self) {
+        let Some(path) = &self.history_path else {
+            return;
+        };
+        if let Some(dir) = path.parent()
+            && let Err(e) = fs::create_dir_all(dir)
+        {
+            eprintln!("Could not save the history to {}: {}", path.display(), e);
+            return;
+        }
+        if let Err(e) = self.editor.save_history(path) {
+            eprintln!("Could not save the history to {}: {}", path.display(), e);
+        }
+    }
+}
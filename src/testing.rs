@@ -0,0 +1,51 @@
+use std::fs;
+use std::path::PathBuf;
+
+use crate::birds::BirdTree;
+
+/// A directory for the files of a single test, which is deleted when the test ends.
+pub struct TestDir {
+    path: PathBuf,
+}
+
+impl TestDir {
+    /// Make an empty directory for a test. Tests run at the same time, so each test needs a
+    /// name of its own.
+    pub fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("formative-{}-{}", std::process::id(), name));
+        // clear out anything left by a test that was stopped part way through
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+
+        Self { path }
+    }
+
+    /// Get the path of a file in the directory, which doesn't need to exist yet.
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.path.join(name)
+    }
+
+    /// Write a file in the directory, returning its path.
+    pub fn file(&self, name: &str, contents: &str) -> PathBuf {
+        let path = self.path(name);
+        fs::write(&path, contents).unwrap();
+        path
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+/// Get the common and scientific names of every bird in a tree, in tree order.
+pub fn bird_names(tree: &BirdTree) -> Vec<(String, String)> {
+    tree.preorder(tree.root())
+        .into_iter()
+        .filter_map(|id| {
+            let scientific_name = tree.binomial_name(id)?.to_string();
+            Some((tree.get(id)?.name().to_string(), scientific_name))
+        })
+        .collect()
+}
@@ -0,0 +1,204 @@
+use std::env;
+use std::io::{IsTerminal, stdout};
+
+use crate::birds::{BirdTree, GroupError, NodeId};
+use crate::config::{Config, Source};
+use crate::file::{self, DroppedRecord, LoadError};
+
+/// How many birds are suggested when a search finds nothing.
+const SUGGESTION_COUNT: usize = 3;
+
+/// Print the details of a bird, using italics for the scientific name in a terminal.
+pub fn print_bird(tree: &BirdTree, bird: NodeId) {
+    if stdout().is_terminal() {
+        println!("\n{:#}\n", tree.display(bird));
+    } else {
+        println!("\n{}\n", tree.display(bird));
+    }
+}
+
+/// Suggest birds with names close to one that could not be found,
+/// e.g. "Did you mean Tūī (Prosthemadera novaeseelandiae)?". Returns None if none are close.
+pub fn suggest_birds(tree: &BirdTree, name: &str) -> Option<String> {
+    let names = tree
+        .fuzzy_search(name, SUGGESTION_COUNT)
+        .iter()
+        .filter_map(|found| {
+            let bird = tree.get(found.id)?.name();
+            Some(match tree.binomial_name(found.id) {
+                Some(binomial_name) => format!("{} ({})", bird, binomial_name),
+                None => bird.to_string(),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    match names.as_slice() {
+        [] => None,
+        [name] => Some(format!("Did you mean {}?", name)),
+        [names @ .., last] => Some(format!("Did you mean {} or {}?", names.join(", "), last)),
+    }
+}
+
+/// Get the reason that a change to a group or bird could not be made.
+/// `name` is the name of the group or bird that the change was made to.
+pub fn group_error_message(e: GroupError, name: &str) -> String {
+    match e {
+        GroupError::NoGroupExistsErr => format!("There is no group with name: {}", name),
+        GroupError::InputOutsideOfBoundsError => {
+            "Please enter a valid string with length 1-50".to_string()
+        }
+        GroupError::NodeTypeError => format!("{} is a bird, so it can't contain anything", name),
+        GroupError::RankOrderError { parent, child } => format!(
+            "A group ranked {} can't be placed below a {}",
+            child, parent
+        ),
+        GroupError::NoBirdExistsErr => format!("There is no bird with name: {}", name),
+        GroupError::GroupNotEmptyError => format!("{} still contains birds or groups", name),
+        GroupError::RootNodeError => {
+            format!("{} is the top of the tree and can't be changed", name)
+        }
+        GroupError::CycleError => format!("{} can't be moved inside itself", name),
+        GroupError::DuplicateNameError => {
+            format!("That name is already used by something other than {}", name)
+        }
+    }
+}
+
+/// Get the reason that a group called `name` could not be added to `parent`.
+pub fn add_group_error_message(e: GroupError, parent: &str, name: &str) -> String {
+    match e {
+        GroupError::DuplicateNameError => format!("There is already a group called {}", name),
+        e => group_error_message(e, parent),
+    }
+}
+
+/// Get the reason that a bird could not be added to `parent`.
+pub fn add_bird_error_message(
+    e: GroupError,
+    parent: &str,
+    name: &str,
+    scientific_name: &str,
+) -> String {
+    match e {
+        GroupError::DuplicateNameError => format!(
+            "There is already a bird called {}, or a bird named {} in {}",
+            name, scientific_name, parent
+        ),
+        e => group_error_message(e, parent),
+    }
+}
+
+/// Check whether the terminal's locale can show Unicode box drawing characters.
+pub fn supports_unicode() -> bool {
+    // the first of these that is set decides the character set
+    ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .find_map(|var| env::var(var).ok().filter(|value| !value.is_empty()))
+        .is_some_and(|locale| {
+            let locale = locale.to_lowercase();
+            locale.contains("utf-8") || locale.contains("utf8")
+        })
+}
+
+/// A way to continue after the data file couldn't be loaded.
+enum Recovery {
+    SkipBadRecords(BirdTree, Vec<DroppedRecord>),
+    Backup(BirdTree),
+    Seed,
+    Exit,
+}
+
+/// Ask how to continue after the data file couldn't be loaded.
+/// Returns None if the user would rather exit and fix the file themselves.
+fn recover_tree(config: &Config, error: &LoadError) -> Option<BirdTree> {
+    let path = &config.data_path;
+    eprintln!("Error: {}", error);
+    if let LoadError::Parse { line, column, .. } = &error
+        && *line > 0
+    {
+        eprintln!("The problem is on line {}, column {}", line, column);
+    }
+
+    // work out which ways of recovering are possible
+    let mut options = vec![];
+    if let LoadError::Parse { .. } | LoadError::Rejected { .. } = error
+        && let Ok((tree, dropped)) = file::load_tree_skipping_bad_records(path)
+    {
+        options.push((
+            format!(
+                "Load the file without the broken records ({})",
+                dropped.len()
+            ),
+            Recovery::SkipBadRecords(tree, dropped),
+        ));
+    }
+    // offer the newest backup that can still be loaded
+    let backups = file::list_backups(path).unwrap_or_default();
+    if let Some((backup, tree)) = backups
+        .iter()
+        .rev()
+        .find_map(|backup| Some((backup, file::load_tree(backup).ok()?)))
+    {
+        options.push((
+            format!(
+                "Load the latest backup ({})",
+                backup.file_name().unwrap_or_default().to_string_lossy()
+            ),
+            Recovery::Backup(tree),
+        ));
+    }
+    options.push(("Start from the built in birds".to_string(), Recovery::Seed));
+    options.push(("Exit".to_string(), Recovery::Exit));
+
+    eprintln!("How would you like to continue?");
+    for (i, (option, _)) in options.iter().enumerate() {
+        eprintln!("{}. {}", i + 1, option);
+    }
+    let choice = crate::get_user_input::<usize>()?.checked_sub(1)?;
+
+    let tree = match options.into_iter().nth(choice)?.1 {
+        Recovery::SkipBadRecords(tree, dropped) => {
+            for record in dropped.iter() {
+                eprintln!(
+                    "Left out {} {}: {}",
+                    record.kind,
+                    record.index + 1,
+                    record.reason
+                );
+            }
+            tree
+        }
+        Recovery::Backup(tree) => tree,
+        Recovery::Seed => file::seed_tree(),
+        Recovery::Exit => return None,
+    };
+
+    // keep the broken file so that anything left out can still be fixed by hand
+    if path.exists() {
+        match file::set_aside(path) {
+            Ok(new_path) => eprintln!("The original file has been moved to {}", new_path.display()),
+            Err(e) => {
+                eprintln!("Could not move the original file somewhere safe: {}", e);
+                return None;
+            }
+        }
+    }
+
+    Some(tree)
+}
+
+/// Load the tree from the data file. When there is no data file yet, the built in taxonomy is
+/// used instead, asking first if the file was chosen by the user in case the path is wrong.
+/// If the file can't be loaded and `recover` is set, the user is asked how to continue,
+/// otherwise the error is returned.
+pub fn load_tree(config: &Config, recover: bool) -> Result<BirdTree, LoadError> {
+    match file::load_tree(&config.data_path) {
+        Ok(tree) => Ok(tree),
+        Err(LoadError::NotFound(path)) if config.data_path_source == Source::Default => {
+            eprintln!("Starting a new bird list at {}", path.display());
+            Ok(file::seed_tree())
+        }
+        Err(e) if recover => recover_tree(config, &e).ok_or(e),
+        Err(e) => Err(e),
+    }
+}